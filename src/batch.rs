@@ -0,0 +1,375 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::Value;
+
+use crate::notification::is_notification;
+use crate::{ErrorObject, Header, Id, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+
+/// A JSON-RPC batch: a non-empty top-level array of messages.
+///
+/// An empty array is an Invalid Request, so neither [`Batch::new`] nor
+/// deserialization will produce an empty batch. When answering a batch made
+/// only of notifications, [`Batch::new`] returns `None`, meaning that nothing
+/// must be sent back.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(transparent)]
+pub struct Batch<T>(Vec<T>);
+
+impl<T> Batch<T> {
+    pub fn new(items: Vec<T>) -> Option<Self> {
+        (!items.is_empty()).then_some(Self(items))
+    }
+
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Always `false`: a batch holds at least one element.
+    pub fn is_empty(&self) -> bool {
+        false
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.0.iter()
+    }
+
+    pub fn as_slice(&self) -> &[T] {
+        &self.0
+    }
+
+    pub fn into_vec(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> Batch<Call<T>> {
+    /// Whether at least one element is a request that must be answered.
+    pub fn expects_response(&self) -> bool {
+        self.iter().any(Call::expects_response)
+    }
+}
+
+impl<T> Batch<BatchEntry<Call<T>>> {
+    /// Whether at least one element is a request or an invalid entry, both of
+    /// which must be answered.
+    pub fn expects_response(&self) -> bool {
+        self.iter().any(BatchEntry::expects_response)
+    }
+}
+
+impl<T> IntoIterator for Batch<T> {
+    type Item = T;
+    type IntoIter = std::vec::IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Batch<T> {
+    type Item = &'a T;
+    type IntoIter = std::slice::Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Batch<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let items = Vec::<T>::deserialize(deserializer)?;
+        Self::new(items).ok_or_else(|| D::Error::custom("empty batch is an Invalid Request"))
+    }
+}
+
+/// A batch element sent by a client: either a request expecting a response,
+/// or a notification.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum Call<T> {
+    Request(JsonRpcRequest<T>),
+    Notification(JsonRpcNotification<T>),
+}
+
+impl<T> Call<T> {
+    pub fn id(&self) -> Option<&Id> {
+        match self {
+            Self::Request(request) => Some(&request.header.id),
+            Self::Notification(_) => None,
+        }
+    }
+
+    pub fn expects_response(&self) -> bool {
+        matches!(self, Self::Request(_))
+    }
+}
+
+impl<'de, T> Deserialize<'de> for Call<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        let Some(object) = value.as_object() else {
+            return Err(D::Error::custom("expected a JSON-RPC request object"));
+        };
+
+        if is_notification(object) {
+            JsonRpcNotification::deserialize(value)
+                .map(Self::Notification)
+                .map_err(D::Error::custom)
+        } else {
+            JsonRpcRequest::deserialize(value)
+                .map(Self::Request)
+                .map_err(D::Error::custom)
+        }
+    }
+}
+
+/// A batch element that either decoded into `T`, or was kept aside as an
+/// [`InvalidEntry`] so that the rest of the batch can still be processed.
+#[derive(Clone, Debug, PartialEq)]
+pub enum BatchEntry<T> {
+    Valid(T),
+    Invalid(InvalidEntry),
+}
+
+impl<T> BatchEntry<T> {
+    pub fn valid(self) -> Option<T> {
+        match self {
+            Self::Valid(item) => Some(item),
+            Self::Invalid(_) => None,
+        }
+    }
+
+    pub fn into_result(self) -> Result<T, InvalidEntry> {
+        match self {
+            Self::Valid(item) => Ok(item),
+            Self::Invalid(entry) => Err(entry),
+        }
+    }
+}
+
+impl<T> BatchEntry<Call<T>> {
+    pub fn expects_response(&self) -> bool {
+        match self {
+            Self::Valid(call) => call.expects_response(),
+            Self::Invalid(_) => true,
+        }
+    }
+}
+
+impl<T> Serialize for BatchEntry<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            Self::Valid(item) => item.serialize(serializer),
+            Self::Invalid(entry) => entry.value.serialize(serializer),
+        }
+    }
+}
+
+impl<'de, T> Deserialize<'de> for BatchEntry<T>
+where
+    T: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = Value::deserialize(deserializer)?;
+        Ok(match T::deserialize(&value) {
+            Ok(item) => Self::Valid(item),
+            Err(error) => Self::Invalid(InvalidEntry::new(value, error.to_string())),
+        })
+    }
+}
+
+/// A batch element that could not be decoded.
+#[derive(Clone, Debug, PartialEq)]
+pub struct InvalidEntry {
+    /// The id of the element, or `Id::Null` when it could not be detected.
+    pub id: Id,
+    /// Why the element was rejected.
+    pub reason: String,
+    /// The element as received.
+    pub value: Value,
+}
+
+impl InvalidEntry {
+    pub fn new(value: Value, reason: impl Into<String>) -> Self {
+        let id = value
+            .get("id")
+            .and_then(|id| Id::deserialize(id).ok())
+            .unwrap_or(Id::Null);
+
+        Self {
+            id,
+            reason: reason.into(),
+            value,
+        }
+    }
+
+    /// Builds the Invalid Request response the specification mandates for
+    /// this element.
+    pub fn to_response<R>(&self) -> JsonRpcResponse<R> {
+        JsonRpcResponse::error(Header::v2(self.id.clone()), ErrorObject::invalid_request())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct TestPayload {
+        method: String,
+    }
+
+    fn payload(method: &str) -> TestPayload {
+        TestPayload {
+            method: method.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_batch_new_rejects_empty() {
+        assert!(Batch::<u32>::new(vec![]).is_none());
+
+        let batch = Batch::new(vec![1, 2]).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.as_slice(), &[1, 2]);
+    }
+
+    #[test]
+    fn test_batch_deserialization_rejects_empty() {
+        let error = serde_json::from_value::<Batch<Call<TestPayload>>>(json!([])).unwrap_err();
+        assert!(error.to_string().contains("Invalid Request"));
+
+        assert!(serde_json::from_value::<Batch<Call<TestPayload>>>(json!({})).is_err());
+    }
+
+    #[test]
+    fn test_batch_request_round_trip() {
+        let json = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "sum"},
+            {"jsonrpc": "2.0", "method": "notify"},
+            {"jsonrpc": "2.0", "id": "2", "method": "get"}
+        ]);
+
+        let batch: Batch<Call<TestPayload>> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            batch.as_slice(),
+            &[
+                Call::Request(JsonRpcRequest {
+                    header: Header::v2(1),
+                    payload: payload("sum"),
+                }),
+                Call::Notification(JsonRpcNotification::v2(payload("notify"))),
+                Call::Request(JsonRpcRequest {
+                    header: Header::v2("2"),
+                    payload: payload("get"),
+                }),
+            ]
+        );
+        assert!(batch.expects_response());
+        assert_eq!(serde_json::to_value(&batch).unwrap(), json);
+    }
+
+    #[test]
+    fn test_batch_of_notifications_expects_no_response() {
+        let json = json!([
+            {"jsonrpc": "2.0", "method": "a"},
+            {"jsonrpc": "2.0", "method": "b"}
+        ]);
+
+        let batch: Batch<Call<TestPayload>> = serde_json::from_value(json).unwrap();
+        assert!(!batch.expects_response());
+
+        let responses: Vec<JsonRpcResponse<()>> = batch
+            .iter()
+            .filter_map(Call::id)
+            .map(|id| JsonRpcResponse::result(Header::v2(id.clone()), ()))
+            .collect();
+        assert!(Batch::new(responses).is_none());
+    }
+
+    #[test]
+    fn test_batch_with_invalid_entries() {
+        let json = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "sum"},
+            {"foo": "boo"},
+            {"jsonrpc": "2.0", "id": "x", "method": 5},
+            1
+        ]);
+
+        let batch: Batch<BatchEntry<Call<TestPayload>>> = serde_json::from_value(json).unwrap();
+        assert_eq!(batch.len(), 4);
+        assert!(batch.expects_response());
+
+        let mut entries = batch.into_iter();
+        assert_eq!(
+            entries.next().unwrap().valid(),
+            Some(Call::Request(JsonRpcRequest {
+                header: Header::v2(1),
+                payload: payload("sum"),
+            }))
+        );
+
+        let invalid: Vec<InvalidEntry> = entries.map(|e| e.into_result().unwrap_err()).collect();
+        assert_eq!(invalid[0].id, Id::Null);
+        assert_eq!(invalid[0].value, json!({"foo": "boo"}));
+        assert_eq!(invalid[1].id, Id::from("x"));
+        assert_eq!(invalid[2].id, Id::Null);
+        assert!(invalid.iter().all(|entry| !entry.reason.is_empty()));
+
+        let response = serde_json::to_value(invalid[1].to_response::<()>()).unwrap();
+        assert_eq!(
+            response,
+            json!({
+                "jsonrpc": "2.0",
+                "id": "x",
+                "error": {"code": -32600, "message": "Invalid Request"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_batch_of_notifications_with_invalid_entry_expects_response() {
+        let json = json!([{"jsonrpc": "2.0", "method": "a"}, []]);
+        let batch: Batch<BatchEntry<Call<TestPayload>>> = serde_json::from_value(json).unwrap();
+        assert!(batch.expects_response());
+    }
+
+    #[test]
+    fn test_batch_response_round_trip() {
+        let json = json!([
+            {"jsonrpc": "2.0", "id": 1, "result": 7},
+            {"jsonrpc": "2.0", "id": null, "error": {"code": -32600, "message": "Invalid Request"}}
+        ]);
+
+        let batch: Batch<JsonRpcResponse<u32>> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(batch.len(), 2);
+        assert_eq!(batch.as_slice()[0].clone().into_result(), Ok(7));
+        assert_eq!(
+            batch.as_slice()[1].clone().into_result(),
+            Err(ErrorObject::invalid_request())
+        );
+        assert_eq!(serde_json::to_value(&batch).unwrap(), json);
+    }
+}
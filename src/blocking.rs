@@ -0,0 +1,552 @@
+//! Runs blocking readers and writers on threads of their own, so that they
+//! can be used wherever an `AsyncRead + AsyncWrite` connection is expected,
+//! whatever the executor.
+
+use std::io::{self, Read, Write};
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+use std::net::Shutdown;
+use std::pin::Pin;
+use std::sync::mpsc as sync_mpsc;
+use std::task::{Context, Poll};
+use std::thread;
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+use std::time::Duration;
+
+use futures::channel::mpsc;
+use futures::executor::block_on;
+use futures::io::{AsyncRead, AsyncWrite};
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+use futures::Stream;
+use futures::{ready, SinkExt, StreamExt};
+
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+use crate::transport::Connection;
+
+const READ_CHUNK: usize = 8 * 1024;
+/// How many chunks may be read ahead before the reader thread waits for them
+/// to be consumed.
+const MAX_QUEUED_READS: usize = 16;
+/// How many writes may be queued before writing waits for the writer thread.
+const MAX_QUEUED_WRITES: usize = 16;
+
+/// A connection over a blocking reader and writer.
+///
+/// Reads are performed ahead on one thread and writes are queued for
+/// another, so neither ever blocks the task polling the connection. Both
+/// only get a few chunks ahead: the reader thread then waits for reads to be
+/// consumed, and writing waits for the writer thread to catch up.
+/// Flushing waits for every queued write to complete, and reports the error
+/// of any that failed; closing flushes before it drops the writer.
+pub struct BlockingIo {
+    incoming: mpsc::Receiver<io::Result<Vec<u8>>>,
+    chunk: Vec<u8>,
+    position: usize,
+    outgoing: Option<sync_mpsc::Sender<Vec<u8>>>,
+    /// The outcome of every write, in order.
+    written: mpsc::UnboundedReceiver<io::Result<()>>,
+    queued: usize,
+    on_drop: Option<Box<dyn FnOnce() + Send>>,
+}
+
+impl BlockingIo {
+    pub fn new<R, W>(reader: R, writer: W) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        let (incoming_sender, incoming) = mpsc::channel(MAX_QUEUED_READS);
+        thread::spawn(move || read_loop(reader, incoming_sender));
+
+        let (outgoing, outgoing_receiver) = sync_mpsc::channel();
+        let (written_sender, written) = mpsc::unbounded();
+        thread::spawn(move || write_loop(writer, outgoing_receiver, written_sender));
+
+        Self {
+            incoming,
+            chunk: Vec::new(),
+            position: 0,
+            outgoing: Some(outgoing),
+            written,
+            queued: 0,
+            on_drop: None,
+        }
+    }
+
+    /// Runs `on_drop` when the connection is dropped, typically to unblock
+    /// the reader thread.
+    pub(crate) fn on_drop(mut self, on_drop: impl FnOnce() + Send + 'static) -> Self {
+        self.on_drop = Some(Box::new(on_drop));
+        self
+    }
+
+    /// Runs a connection over `socket`, whose writing half is shut down once
+    /// everything queued has been written, and whose reading half is shut
+    /// down when the connection is dropped, unblocking the reader thread.
+    #[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+    pub(crate) fn socket<S>(socket: S) -> io::Result<Self>
+    where
+        S: Socket,
+    {
+        let reader = socket.try_clone()?;
+        let writer = SocketWriter(socket.try_clone()?);
+        Ok(Self::new(reader, writer).on_drop(move || {
+            let _ = socket.shutdown(Shutdown::Read);
+        }))
+    }
+
+    /// Waits for the oldest queued write to complete.
+    fn poll_written(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let written = ready!(self.written.poll_next_unpin(cx));
+        self.queued -= 1;
+        // The writer thread only stops early after a failed write.
+        Poll::Ready(written.unwrap_or_else(|| Err(io::ErrorKind::BrokenPipe.into())))
+    }
+}
+
+impl Drop for BlockingIo {
+    fn drop(&mut self) {
+        if let Some(on_drop) = self.on_drop.take() {
+            on_drop();
+        }
+    }
+}
+
+impl AsyncRead for BlockingIo {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        while self.position == self.chunk.len() {
+            match self.incoming.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(chunk))) => {
+                    self.chunk = chunk;
+                    self.position = 0;
+                }
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Err(error)),
+                Poll::Ready(None) => return Poll::Ready(Ok(0)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        let this = &mut *self;
+        let available = &this.chunk[this.position..];
+        let read = available.len().min(buf.len());
+        buf[..read].copy_from_slice(&available[..read]);
+        this.position += read;
+        Poll::Ready(Ok(read))
+    }
+}
+
+impl AsyncWrite for BlockingIo {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        while self.queued >= MAX_QUEUED_WRITES {
+            ready!(self.poll_written(cx))?;
+        }
+
+        let queued = self
+            .outgoing
+            .as_ref()
+            .is_some_and(|outgoing| outgoing.send(buf.to_vec()).is_ok());
+        if queued {
+            self.queued += 1;
+            Poll::Ready(Ok(buf.len()))
+        } else {
+            Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()))
+        }
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        while self.queued > 0 {
+            ready!(self.poll_written(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let flushed = ready!(self.as_mut().poll_flush(cx));
+        self.outgoing = None;
+        Poll::Ready(flushed)
+    }
+}
+
+/// A connected socket whose halves can be shut down independently.
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+pub(crate) trait Socket: Read + Write + Send + Sized + 'static {
+    fn try_clone(&self) -> io::Result<Self>;
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()>;
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+/// A socket accepting connections.
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+pub(crate) trait Listener: Send + 'static {
+    type Socket: Socket;
+    /// The address of the peer of an accepted connection.
+    type Addr;
+
+    fn accept(&self) -> io::Result<(Self::Socket, Self::Addr)>;
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()>;
+}
+
+#[cfg(feature = "tcp")]
+impl Socket for std::net::TcpStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        self.try_clone()
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.shutdown(how)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.set_nonblocking(nonblocking)
+    }
+}
+
+#[cfg(feature = "tcp")]
+impl Listener for std::net::TcpListener {
+    type Socket = std::net::TcpStream;
+    type Addr = std::net::SocketAddr;
+
+    fn accept(&self) -> io::Result<(Self::Socket, Self::Addr)> {
+        self.accept()
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.set_nonblocking(nonblocking)
+    }
+}
+
+#[cfg(all(unix, feature = "unix"))]
+impl Socket for std::os::unix::net::UnixStream {
+    fn try_clone(&self) -> io::Result<Self> {
+        self.try_clone()
+    }
+
+    fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        self.shutdown(how)
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.set_nonblocking(nonblocking)
+    }
+}
+
+#[cfg(all(unix, feature = "unix"))]
+impl Listener for std::os::unix::net::UnixListener {
+    type Socket = std::os::unix::net::UnixStream;
+    type Addr = std::os::unix::net::SocketAddr;
+
+    fn accept(&self) -> io::Result<(Self::Socket, Self::Addr)> {
+        self.accept()
+    }
+
+    fn set_nonblocking(&self, nonblocking: bool) -> io::Result<()> {
+        self.set_nonblocking(nonblocking)
+    }
+}
+
+/// The writing half of a socket, shut down once the writer is done.
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+struct SocketWriter<S: Socket>(S);
+
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+impl<S: Socket> Write for SocketWriter<S> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+impl<S: Socket> Drop for SocketWriter<S> {
+    fn drop(&mut self) {
+        let _ = self.0.shutdown(Shutdown::Write);
+    }
+}
+
+/// How often the accept thread checks whether its stream was dropped while
+/// no connection comes in.
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+const ACCEPT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+/// The longest the accept thread waits before retrying after a failure.
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+const MAX_ACCEPT_BACKOFF: Duration = Duration::from_secs(1);
+
+/// Connections accepted on a thread of their own.
+///
+/// Failures to accept are yielded as errors, and the thread backs off for a
+/// while after each of them, so that a listener that keeps failing, such as
+/// one out of file descriptors, doesn't spin. Dropping the stream stops the
+/// thread and closes the listener.
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+pub struct Incoming<T> {
+    receiver: mpsc::Receiver<io::Result<Connection<T>>>,
+}
+
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+impl<T> Incoming<T>
+where
+    T: Send + 'static,
+{
+    /// Accepts connections from `listener` on a new thread, turning each of
+    /// them into a [`Connection`] with `connect`, until the stream is
+    /// dropped.
+    pub(crate) fn spawn<L, F>(listener: L, mut connect: F) -> Self
+    where
+        L: Listener,
+        F: FnMut(L::Socket, L::Addr) -> io::Result<Connection<T>> + Send + 'static,
+    {
+        let (mut sender, receiver) = mpsc::channel(0);
+        thread::spawn(move || {
+            // A nonblocking listener lets the thread notice the stream being
+            // dropped without waiting for another connection.
+            if let Err(error) = listener.set_nonblocking(true) {
+                let _ = block_on(sender.send(Err(error)));
+                return;
+            }
+
+            let mut backoff = ACCEPT_POLL_INTERVAL;
+            while !sender.is_closed() {
+                let accepted = listener.accept().and_then(|(socket, addr)| {
+                    socket.set_nonblocking(false)?;
+                    connect(socket, addr)
+                });
+                match accepted {
+                    Err(error) if error.kind() == io::ErrorKind::WouldBlock => {
+                        thread::sleep(ACCEPT_POLL_INTERVAL);
+                        continue;
+                    }
+                    Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                    _ => {}
+                }
+
+                let failed = accepted.is_err();
+                if block_on(sender.send(accepted)).is_err() {
+                    return;
+                }
+                if failed {
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(MAX_ACCEPT_BACKOFF);
+                } else {
+                    backoff = ACCEPT_POLL_INTERVAL;
+                }
+            }
+        });
+        Self { receiver }
+    }
+}
+
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+impl<T> Stream for Incoming<T> {
+    type Item = io::Result<Connection<T>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx)
+    }
+}
+
+fn read_loop<R>(mut reader: R, mut incoming: mpsc::Sender<io::Result<Vec<u8>>>)
+where
+    R: Read,
+{
+    let mut buffer = vec![0; READ_CHUNK];
+    loop {
+        let chunk = match reader.read(&mut buffer) {
+            Ok(0) => return,
+            Ok(read) => Ok(buffer[..read].to_vec()),
+            Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+            Err(error) => Err(error),
+        };
+
+        let failed = chunk.is_err();
+        if block_on(incoming.send(chunk)).is_err() || failed {
+            return;
+        }
+    }
+}
+
+fn write_loop<W>(
+    mut writer: W,
+    outgoing: sync_mpsc::Receiver<Vec<u8>>,
+    written: mpsc::UnboundedSender<io::Result<()>>,
+) where
+    W: Write,
+{
+    for chunk in outgoing {
+        let result = writer.write_all(&chunk).and_then(|()| writer.flush());
+        let failed = result.is_err();
+        // What was queued is written out even if the connection is dropped.
+        let _ = written.unbounded_send(result);
+        if failed {
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::io::{AsyncReadExt, AsyncWriteExt};
+    use futures::FutureExt;
+    use std::io::Cursor;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::time::Duration;
+
+    #[derive(Clone, Default)]
+    struct SharedWriter(Arc<Mutex<Vec<u8>>>);
+
+    impl Write for SharedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_blocking_io() {
+        let output = SharedWriter::default();
+        let mut io = BlockingIo::new(Cursor::new(b"hello".to_vec()), output.clone());
+
+        let mut input = String::new();
+        block_on(io.read_to_string(&mut input)).unwrap();
+        assert_eq!(input, "hello");
+
+        block_on(io.write_all(b"world")).unwrap();
+        block_on(io.close()).unwrap();
+        assert!(block_on(io.write_all(b"!")).is_err());
+
+        // The writer thread drops its end once everything has been written.
+        while Arc::strong_count(&output.0) > 1 {
+            thread::yield_now();
+        }
+        assert_eq!(*output.0.lock().unwrap(), b"world");
+    }
+
+    /// A writer whose every write waits for a go-ahead, then fails once the
+    /// go-aheads run out.
+    struct GatedWriter(sync_mpsc::Receiver<()>);
+
+    impl Write for GatedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.0
+                .recv()
+                .map(|()| buf.len())
+                .map_err(|_| io::ErrorKind::ConnectionReset.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    /// A reader that never runs out, counting how often it is read.
+    struct CountingReader(Arc<AtomicUsize>);
+
+    impl Read for CountingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Ok(buf.len())
+        }
+    }
+
+    #[test]
+    fn test_blocking_io_read_backpressure() {
+        let reads = Arc::new(AtomicUsize::new(0));
+        let mut io = BlockingIo::new(CountingReader(reads.clone()), io::sink());
+        thread::sleep(Duration::from_millis(50));
+
+        // The reader thread waits with the queue full, plus the chunk it is
+        // trying to queue.
+        let queued = reads.load(Ordering::SeqCst);
+        assert!(queued <= MAX_QUEUED_READS + 2);
+        let mut buf = vec![0; READ_CHUNK];
+        block_on(io.read_exact(&mut buf)).unwrap();
+        thread::sleep(Duration::from_millis(50));
+        assert!(reads.load(Ordering::SeqCst) <= queued + 1);
+    }
+
+    #[test]
+    fn test_blocking_io_write_backpressure() {
+        let (gate, gated) = sync_mpsc::channel();
+        let mut io = BlockingIo::new(io::empty(), GatedWriter(gated));
+
+        for _ in 0..MAX_QUEUED_WRITES {
+            block_on(io.write(b"a")).unwrap();
+        }
+        assert!(io.write(b"a").now_or_never().is_none());
+        assert!(io.flush().now_or_never().is_none());
+
+        gate.send(()).unwrap();
+        block_on(io.write(b"a")).unwrap();
+
+        // Flushing reports the failure of a queued write.
+        for _ in 0..MAX_QUEUED_WRITES - 1 {
+            gate.send(()).unwrap();
+        }
+        drop(gate);
+        let error = block_on(io.flush()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::ConnectionReset);
+        let written = block_on(async {
+            io.write_all(b"a").await?;
+            io.flush().await
+        });
+        assert_eq!(written.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[cfg(feature = "tcp")]
+    #[test]
+    fn test_incoming_backs_off_after_failures() {
+        /// A listener out of file descriptors.
+        struct Exhausted(Arc<AtomicUsize>);
+
+        impl Listener for Exhausted {
+            type Socket = std::net::TcpStream;
+            type Addr = ();
+
+            fn accept(&self) -> io::Result<(Self::Socket, Self::Addr)> {
+                self.0.fetch_add(1, Ordering::SeqCst);
+                Err(io::Error::from_raw_os_error(24))
+            }
+
+            fn set_nonblocking(&self, _: bool) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let accepts = Arc::new(AtomicUsize::new(0));
+        let mut incoming = Incoming::<()>::spawn(Exhausted(accepts.clone()), |_, ()| {
+            unreachable!("nothing is accepted")
+        });
+        let start = std::time::Instant::now();
+        while start.elapsed() < Duration::from_millis(200) {
+            assert!(block_on(incoming.next()).unwrap().is_err());
+        }
+        assert!(accepts.load(Ordering::SeqCst) <= 6);
+
+        // Dropping the stream stops the thread, and with it the listener.
+        drop(incoming);
+        let deadline = std::time::Instant::now() + Duration::from_secs(5);
+        while Arc::strong_count(&accepts) > 1 {
+            assert!(std::time::Instant::now() < deadline);
+            thread::yield_now();
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+
+use crate::{ErrorObject, Id};
+
+/// How requests are cancelled: a [`Client`](crate::Client) abandoning a call
+/// sends a notification for `method` with the id of the request, and a
+/// [`Server`](crate::Server) receiving it aborts the handler of that request,
+/// answering it with `error_code`.
+///
+/// The defaults follow LSP: `$/cancelRequest` with params `{"id": ...}`,
+/// answered with -32800.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Cancellation {
+    method: Arc<str>,
+    error_code: i64,
+}
+
+impl Cancellation {
+    pub const DEFAULT_METHOD: &'static str = "$/cancelRequest";
+    pub const DEFAULT_ERROR_CODE: i64 = -32800;
+
+    pub fn new(method: &str, error_code: i64) -> Self {
+        Self {
+            method: method.into(),
+            error_code,
+        }
+    }
+
+    pub fn method(&self) -> &str {
+        &self.method
+    }
+
+    pub fn error_code(&self) -> i64 {
+        self.error_code
+    }
+
+    /// The error answering a cancelled request.
+    pub fn error(&self) -> ErrorObject {
+        ErrorObject::new(self.error_code, "Request cancelled")
+    }
+
+    /// The notification cancelling the request `id`.
+    #[cfg(feature = "client")]
+    pub(crate) fn notification(&self, id: &Id) -> serde_json::Value {
+        serde_json::json!({"jsonrpc": "2.0", "method": self.method(), "params": {"id": id}})
+    }
+
+    /// The id of the request cancelled by `request`, if it is a cancellation.
+    #[cfg(feature = "server")]
+    pub(crate) fn cancelled_id(&self, request: &crate::Request) -> Option<Id> {
+        #[derive(serde::Deserialize)]
+        struct CancelParams {
+            id: Id,
+        }
+
+        if request.method != self.method() {
+            return None;
+        }
+        request
+            .parse_params::<CancelParams>()
+            .ok()
+            .map(|params| params.id)
+    }
+}
+
+impl Default for Cancellation {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_METHOD, Self::DEFAULT_ERROR_CODE)
+    }
+}
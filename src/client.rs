@@ -0,0 +1,592 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use futures::channel::{mpsc, oneshot};
+use futures::{SinkExt, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::transport::{Transport, TransportError};
+use crate::{
+    BatchEntry, Cancellation, ErrorObject, Header, Id, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse, Message, Method, Params, Request,
+};
+
+/// Errors returned by [`Client`] calls.
+#[derive(Debug)]
+pub enum ClientError<E = ErrorObject> {
+    /// The connection closed before the response arrived.
+    Closed,
+    /// The connection failed before the response arrived. Every request
+    /// pending at the time shares the error, unless the transport reported
+    /// the failure for some requests only, with
+    /// [`TransportError::Unanswered`].
+    Transport(Arc<io::Error>),
+    /// The parameters could not be serialized.
+    Serialize(serde_json::Error),
+    /// The response didn't match the expected result or error type.
+    Deserialize(serde_json::Error),
+    /// The server answered with an error.
+    Rpc(E),
+}
+
+impl<E> fmt::Display for ClientError<E>
+where
+    E: fmt::Display,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => f.write_str("connection closed before the response arrived"),
+            Self::Transport(error) => write!(f, "connection failed: {error}"),
+            Self::Serialize(error) => write!(f, "failed to serialize params: {error}"),
+            Self::Deserialize(error) => write!(f, "failed to deserialize response: {error}"),
+            Self::Rpc(error) => write!(f, "server error: {error}"),
+        }
+    }
+}
+
+impl<E> std::error::Error for ClientError<E> where E: fmt::Debug + fmt::Display {}
+
+type PendingMap =
+    HashMap<Id, oneshot::Sender<Result<JsonRpcResponse<Value, Value>, Arc<io::Error>>>>;
+type Pending = Arc<Mutex<PendingMap>>;
+
+/// A JSON-RPC client that assigns request ids and correlates responses.
+///
+/// Outgoing messages are queued on the receiver returned by [`Client::new`];
+/// incoming responses are handed back through [`Client::handle_response`], in
+/// any order. [`Client::connect`] does both over a [`Transport`].
+///
+/// Dropping the future of a call before its response arrives cancels the
+/// request, as described by [`Cancellation`].
+#[derive(Clone)]
+pub struct Client {
+    next_id: Arc<AtomicU64>,
+    pending: Pending,
+    outgoing: mpsc::UnboundedSender<Value>,
+    cancellation: Option<Cancellation>,
+}
+
+impl Client {
+    pub fn new() -> (Self, mpsc::UnboundedReceiver<Value>) {
+        let (outgoing, receiver) = mpsc::unbounded();
+        let client = Self {
+            next_id: Arc::new(AtomicU64::new(1)),
+            pending: Pending::default(),
+            outgoing,
+            cancellation: Some(Cancellation::default()),
+        };
+
+        (client, receiver)
+    }
+
+    /// Sets how abandoned calls are cancelled; `None` sends nothing. Clones
+    /// made before keep their own setting.
+    pub fn with_cancellation(mut self, cancellation: Option<Cancellation>) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn cancellation(&self) -> Option<&Cancellation> {
+        self.cancellation.as_ref()
+    }
+
+    /// Connects a client to `transport`.
+    ///
+    /// The returned future drives the connection and must be polled, usually
+    /// by spawning it. It completes once every clone of the client is dropped
+    /// or the transport closes, failing whatever requests are still pending:
+    /// with [`ClientError::Transport`] if the transport failed, or
+    /// [`ClientError::Closed`] otherwise.
+    pub fn connect<T>(
+        transport: T,
+    ) -> (
+        Self,
+        impl Future<Output = Result<(), TransportError>> + Send + 'static,
+    )
+    where
+        T: Transport,
+    {
+        let (client, outgoing) = Self::new();
+        let driver = drive(transport, outgoing, client.pending.clone());
+        (client, driver)
+    }
+
+    /// Sends a request for `method` and waits for its response.
+    ///
+    /// `params` must serialize to an array or an object, or to `null` to be
+    /// left out, as with `()`; anything else fails with
+    /// [`ClientError::Serialize`].
+    pub async fn request<P, R>(&self, method: &str, params: P) -> Result<R, ClientError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+    {
+        let request = payload(method, params).map_err(ClientError::Serialize)?;
+        self.send_request(request).await
+    }
+
+    /// Sends a request for the method `M` and waits for its response.
+    pub async fn call<M>(&self, params: M::Params) -> Result<M::Result, ClientError<M::Error>>
+    where
+        M: Method,
+        M::Params: Serialize,
+        M::Result: DeserializeOwned,
+        M::Error: DeserializeOwned,
+    {
+        let request = payload(M::NAME, params).map_err(ClientError::Serialize)?;
+        self.send_request(request).await
+    }
+
+    /// Sends a notification for `method`; no response is expected. `params`
+    /// are checked as by [`Client::request`].
+    pub fn notify<P>(&self, method: &str, params: P) -> Result<(), ClientError>
+    where
+        P: Serialize,
+    {
+        let notification =
+            JsonRpcNotification::v2(payload(method, params).map_err(ClientError::Serialize)?);
+        let message = serde_json::to_value(notification).map_err(ClientError::Serialize)?;
+        self.outgoing
+            .unbounded_send(message)
+            .map_err(|_| ClientError::Closed)
+    }
+
+    /// Resolves the pending request matching the response id.
+    ///
+    /// Returns `false` when no request is waiting for that id.
+    pub fn handle_response(&self, response: JsonRpcResponse<Value, Value>) -> bool {
+        resolve(&self.pending, response)
+    }
+
+    /// The number of requests waiting for a response.
+    pub fn pending_requests(&self) -> usize {
+        lock(&self.pending).len()
+    }
+
+    /// Fails every pending request with [`ClientError::Closed`].
+    pub fn close(&self) {
+        lock(&self.pending).clear();
+    }
+
+    /// Queues messages for the remote side alongside the client's own.
+    #[cfg(feature = "server")]
+    pub(crate) fn outgoing(&self) -> mpsc::UnboundedSender<Value> {
+        self.outgoing.clone()
+    }
+
+    /// Fails the pending requests with these ids, which will get no response.
+    #[cfg(feature = "server")]
+    pub(crate) fn fail(&self, ids: &[Id], error: io::Error) {
+        fail(&self.pending, ids, error);
+    }
+
+    fn next_id(&self) -> Id {
+        Id::from(self.next_id.fetch_add(1, Ordering::Relaxed))
+    }
+
+    async fn send_request<P, R, E>(&self, request: Request<P>) -> Result<R, ClientError<E>>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let id = self.next_id();
+        let request = JsonRpcRequest {
+            header: Header::v2(id.clone()),
+            payload: request,
+        };
+        let message = serde_json::to_value(request).map_err(ClientError::Serialize)?;
+
+        let (sender, receiver) = oneshot::channel();
+        lock(&self.pending).insert(id.clone(), sender);
+        let guard = PendingGuard {
+            client: self,
+            id: Some(id),
+        };
+
+        self.outgoing
+            .unbounded_send(message)
+            .map_err(|_| ClientError::Closed)?;
+
+        let response = receiver
+            .await
+            .map_err(|_| ClientError::Closed)?
+            .map_err(ClientError::Transport)?;
+        guard.complete();
+
+        match response.into_result() {
+            Ok(result) => serde_json::from_value(result).map_err(ClientError::Deserialize),
+            Err(error) => Err(serde_json::from_value(error)
+                .map_or_else(ClientError::Deserialize, ClientError::Rpc)),
+        }
+    }
+}
+
+/// Removes the pending entry of a request whose future is dropped before its
+/// response arrives, and cancels the request.
+struct PendingGuard<'a> {
+    client: &'a Client,
+    id: Option<Id>,
+}
+
+impl PendingGuard<'_> {
+    fn complete(mut self) {
+        self.id = None;
+    }
+}
+
+impl Drop for PendingGuard<'_> {
+    fn drop(&mut self) {
+        let Some(id) = self.id.take() else {
+            return;
+        };
+        let abandoned = lock(&self.client.pending).remove(&id).is_some();
+        if let (true, Some(cancellation)) = (abandoned, &self.client.cancellation) {
+            let _ = self
+                .client
+                .outgoing
+                .unbounded_send(cancellation.notification(&id));
+        }
+    }
+}
+
+/// Builds the payload of a call to `method`, leaving `params` out when they
+/// serialize to `null`. Otherwise they must be an array or an object.
+fn payload<P>(method: &str, params: P) -> Result<Request, serde_json::Error>
+where
+    P: Serialize,
+{
+    let params = match serde_json::to_value(params)? {
+        Value::Null => None,
+        value => Some(Params::try_from(value).map_err(|_| {
+            serde::ser::Error::custom("params must be an array, an object or null")
+        })?),
+    };
+    Ok(Request {
+        method: method.to_string(),
+        params,
+    })
+}
+
+fn lock(pending: &Pending) -> MutexGuard<'_, PendingMap> {
+    pending.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+fn resolve(pending: &Pending, response: JsonRpcResponse<Value, Value>) -> bool {
+    let sender = lock(pending).remove(&response.0.header.id);
+    match sender {
+        Some(sender) => sender.send(Ok(response)).is_ok(),
+        None => false,
+    }
+}
+
+/// Fails the pending requests with these ids, which will get no response.
+fn fail(pending: &Pending, ids: &[Id], error: io::Error) {
+    let error = Arc::new(error);
+    let mut pending = lock(pending);
+    for id in ids {
+        if let Some(sender) = pending.remove(id) {
+            let _ = sender.send(Err(Arc::clone(&error)));
+        }
+    }
+}
+
+/// Forwards outgoing messages to `transport` and resolves pending requests
+/// with the responses it yields.
+async fn drive<T>(
+    transport: T,
+    mut outgoing: mpsc::UnboundedReceiver<Value>,
+    pending: Pending,
+) -> Result<(), TransportError>
+where
+    T: Transport,
+{
+    let (mut sink, stream) = transport.split();
+    let mut stream = stream.fuse();
+    let outcome = loop {
+        futures::select! {
+            message = outgoing.next() => match message {
+                Some(message) => {
+                    if let Err(error) = sink.send(message).await {
+                        break Err(error);
+                    }
+                }
+                None => break sink.close().await,
+            },
+            message = stream.next() => match message {
+                Some(Ok(message)) => {
+                    for response in responses(message) {
+                        resolve(&pending, response);
+                    }
+                }
+                // Unparseable messages can't be matched to a request.
+                Some(Err(TransportError::Parse(_))) => {}
+                Some(Err(TransportError::Unanswered { ids, error })) => {
+                    fail(&pending, &ids, error);
+                }
+                Some(Err(error)) => break Err(error),
+                None => break Ok(()),
+            },
+        }
+    };
+
+    match outcome {
+        Err(TransportError::Io(error)) => {
+            let error = Arc::new(error);
+            for (_, sender) in lock(&pending).drain() {
+                let _ = sender.send(Err(Arc::clone(&error)));
+            }
+            Err(TransportError::Io(io::Error::new(error.kind(), error)))
+        }
+        outcome => {
+            lock(&pending).clear();
+            outcome
+        }
+    }
+}
+
+/// Extracts the responses carried by an incoming message, ignoring anything
+/// else.
+fn responses(message: Value) -> Vec<JsonRpcResponse<Value, Value>> {
+    match serde_json::from_value::<Message<Value, Value, Value>>(message) {
+        Ok(Message::Response(response)) => vec![response],
+        Ok(Message::Batch(batch)) => batch
+            .into_iter()
+            .filter_map(|entry| match entry {
+                BatchEntry::Valid(Message::Response(response)) => Some(response),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::{future, FutureExt, StreamExt};
+    use serde_json::json;
+
+    struct Subtract;
+
+    impl Method for Subtract {
+        const NAME: &'static str = "subtract";
+        type Params = (i64, i64);
+        type Result = i64;
+        type Error = ErrorObject<String>;
+    }
+
+    fn respond(request: &Value, response: Value) -> JsonRpcResponse<Value, Value> {
+        let mut response = response;
+        response["jsonrpc"] = json!("2.0");
+        response["id"] = request["id"].clone();
+        serde_json::from_value(response).unwrap()
+    }
+
+    #[test]
+    fn test_client_assigns_ids() {
+        let (client, mut outgoing) = Client::new();
+
+        let mut first = client.request::<_, i64>("a", [1]).boxed();
+        let mut second = client.request::<_, i64>("b", [2]).boxed();
+        assert!(block_on(future::poll_immediate(&mut first)).is_none());
+        assert!(block_on(future::poll_immediate(&mut second)).is_none());
+
+        let first = block_on(outgoing.next()).unwrap();
+        let second = block_on(outgoing.next()).unwrap();
+        assert_eq!(
+            first,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "a", "params": [1]})
+        );
+        assert_eq!(second["id"], json!(2));
+    }
+
+    #[test]
+    fn test_client_out_of_order_responses() {
+        let (client, mut outgoing) = Client::new();
+
+        let server = {
+            let client = client.clone();
+            async move {
+                let first = outgoing.next().await.unwrap();
+                let second = outgoing.next().await.unwrap();
+                assert!(client.handle_response(respond(&second, json!({"result": 20}))));
+                assert!(client.handle_response(respond(&first, json!({"result": 10}))));
+            }
+        };
+
+        let (first, second, ()) = block_on(future::join3(
+            client.request::<_, i64>("first", [1]),
+            client.request::<_, i64>("second", [2]),
+            server,
+        ));
+        assert_eq!(first.unwrap(), 10);
+        assert_eq!(second.unwrap(), 20);
+        assert_eq!(client.pending_requests(), 0);
+    }
+
+    #[test]
+    fn test_client_typed_call() {
+        let (client, mut outgoing) = Client::new();
+
+        let server = {
+            let client = client.clone();
+            async move {
+                let request = outgoing.next().await.unwrap();
+                assert_eq!(request["method"], json!("subtract"));
+                assert_eq!(request["params"], json!([42, 23]));
+                client.handle_response(respond(&request, json!({"result": 19})));
+
+                let request = outgoing.next().await.unwrap();
+                client.handle_response(respond(
+                    &request,
+                    json!({"error": {"code": -32001, "message": "Busy", "data": "later"}}),
+                ));
+            }
+        };
+
+        let (ok, err, ()) = block_on(future::join3(
+            client.call::<Subtract>((42, 23)),
+            client.call::<Subtract>((1, 1)),
+            server,
+        ));
+        assert_eq!(ok.unwrap(), 19);
+        match err {
+            Err(ClientError::Rpc(error)) => {
+                assert_eq!(error.code, -32001);
+                assert_eq!(error.data.as_deref(), Some("later"));
+            }
+            other => panic!("unexpected outcome: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn test_client_result_type_mismatch() {
+        let (client, mut outgoing) = Client::new();
+
+        let server = {
+            let client = client.clone();
+            async move {
+                let request = outgoing.next().await.unwrap();
+                client.handle_response(respond(&request, json!({"result": "nineteen"})));
+            }
+        };
+
+        let (result, ()) = block_on(future::join(client.call::<Subtract>((42, 23)), server));
+        assert!(matches!(result, Err(ClientError::Deserialize(_))));
+    }
+
+    #[test]
+    fn test_client_params() {
+        let (client, mut outgoing) = Client::new();
+        client.notify("exit", ()).unwrap();
+        let message = block_on(outgoing.next()).unwrap();
+        assert_eq!(message, json!({"jsonrpc": "2.0", "method": "exit"}));
+
+        let error = block_on(client.request::<_, ()>("shutdown", 1)).unwrap_err();
+        assert!(matches!(error, ClientError::Serialize(_)));
+        assert!(matches!(
+            client.notify("exit", "now"),
+            Err(ClientError::Serialize(_))
+        ));
+
+        let (request, ()) = block_on(future::join(
+            client.request::<_, ()>("shutdown", ()),
+            async {
+                let request = outgoing.next().await.unwrap();
+                assert_eq!(request.get("params"), None);
+                client.handle_response(respond(&request, json!({"result": null})));
+            },
+        ));
+        request.unwrap();
+    }
+
+    #[test]
+    fn test_client_notify() {
+        let (client, mut outgoing) = Client::new();
+        client.notify("update", json!({"value": 1})).unwrap();
+
+        let message = block_on(outgoing.next()).unwrap();
+        assert_eq!(
+            message,
+            json!({"jsonrpc": "2.0", "method": "update", "params": {"value": 1}})
+        );
+        assert_eq!(client.pending_requests(), 0);
+    }
+
+    #[test]
+    fn test_client_unknown_response() {
+        let (client, _outgoing) = Client::new();
+        let response = JsonRpcResponse::result(Header::v2(99), json!(1));
+        assert!(!client.handle_response(response));
+    }
+
+    #[test]
+    fn test_client_closed() {
+        let (client, outgoing) = Client::new();
+        drop(outgoing);
+
+        let result = block_on(client.request::<_, i64>("a", [1]));
+        assert!(matches!(result, Err(ClientError::Closed)));
+        assert!(matches!(client.notify("a", [1]), Err(ClientError::Closed)));
+        assert_eq!(client.pending_requests(), 0);
+    }
+
+    #[test]
+    fn test_client_close_fails_pending() {
+        let (client, mut outgoing) = Client::new();
+
+        let server = {
+            let client = client.clone();
+            async move {
+                outgoing.next().await.unwrap();
+                assert_eq!(client.pending_requests(), 1);
+                client.close();
+            }
+        };
+
+        let (result, ()) = block_on(future::join(client.request::<_, i64>("a", [1]), server));
+        assert!(matches!(result, Err(ClientError::Closed)));
+    }
+
+    #[test]
+    fn test_client_dropped_request_is_forgotten() {
+        let (client, _outgoing) = Client::new();
+
+        let mut request = client.request::<_, i64>("a", [1]).boxed();
+        assert!(block_on(future::poll_immediate(&mut request)).is_none());
+        assert_eq!(client.pending_requests(), 1);
+
+        drop(request);
+        assert_eq!(client.pending_requests(), 0);
+    }
+
+    #[test]
+    fn test_client_cancels_dropped_request() {
+        let (client, mut outgoing) = Client::new();
+
+        let mut request = client.request::<_, i64>("a", [1]).boxed();
+        assert!(block_on(future::poll_immediate(&mut request)).is_none());
+        drop(request);
+        assert_eq!(block_on(outgoing.next()).unwrap()["id"], json!(1));
+        assert_eq!(
+            block_on(outgoing.next()).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": 1}})
+        );
+
+        let client = client.with_cancellation(None);
+        let mut request = client.request::<_, i64>("a", [2]).boxed();
+        assert!(block_on(future::poll_immediate(&mut request)).is_none());
+        drop(request);
+        drop(client);
+        assert_eq!(block_on(outgoing.next()).unwrap()["id"], json!(2));
+        assert!(block_on(outgoing.next()).is_none());
+    }
+}
@@ -0,0 +1,302 @@
+//! Codecs frame JSON-RPC messages on byte streams, and [`Framed`] turns any
+//! `AsyncRead + AsyncWrite` connection into a [`Transport`](crate::transport::Transport)
+//! with one of them.
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite};
+use futures::{ready, Sink, Stream};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::transport::TransportError;
+
+mod concatenated;
+mod content_length;
+mod ndjson;
+#[cfg(all(test, any(feature = "server", feature = "websocket")))]
+pub(crate) mod testing;
+
+pub use concatenated::Concatenated;
+pub use content_length::ContentLength;
+pub use ndjson::Ndjson;
+
+/// Errors raised while encoding or decoding a frame.
+#[derive(Debug)]
+pub enum CodecError {
+    /// The framing itself is broken, so the stream can't be read any further.
+    InvalidFrame(String),
+    /// The content of a frame isn't valid JSON, or doesn't match the expected
+    /// type. The frame has been consumed: the next one can still be decoded.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for CodecError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidFrame(reason) => write!(f, "invalid frame: {reason}"),
+            Self::Json(error) => write!(f, "invalid frame content: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for CodecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::InvalidFrame(_) => None,
+            Self::Json(error) => Some(error),
+        }
+    }
+}
+
+impl From<CodecError> for TransportError {
+    fn from(error: CodecError) -> Self {
+        match error {
+            CodecError::InvalidFrame(_) => {
+                Self::Io(io::Error::new(io::ErrorKind::InvalidData, error))
+            }
+            CodecError::Json(error) => Self::Parse(error),
+        }
+    }
+}
+
+/// Splits a byte stream into messages, and writes messages back to one.
+pub trait Codec {
+    /// Appends the frame for `item` to `dst`.
+    fn encode<T>(&mut self, item: &T, dst: &mut Vec<u8>) -> Result<(), CodecError>
+    where
+        T: Serialize;
+
+    /// Decodes the next message from `src`, removing the bytes it consumed.
+    ///
+    /// Returns `None` when `src` doesn't hold a complete frame yet.
+    fn decode<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned;
+
+    /// Decodes the next message once the stream has ended, when no more bytes
+    /// will be appended to `src`.
+    ///
+    /// By default, bytes left over after the last complete frame are an error.
+    fn decode_eof<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() => Ok(None),
+            None => {
+                src.clear();
+                Err(CodecError::InvalidFrame(
+                    "stream ended in the middle of a frame".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+const READ_CHUNK: usize = 8 * 1024;
+const WRITE_HIGH_WATER_MARK: usize = 8 * 1024;
+
+/// A [`Transport`](crate::transport::Transport) over a byte stream, framed by
+/// a [`Codec`].
+pub struct Framed<T, C> {
+    io: T,
+    codec: C,
+    read_buffer: Vec<u8>,
+    write_buffer: Vec<u8>,
+    eof: bool,
+}
+
+impl<T, C> Framed<T, C> {
+    pub fn new(io: T, codec: C) -> Self {
+        Self {
+            io,
+            codec,
+            read_buffer: Vec::new(),
+            write_buffer: Vec::new(),
+            eof: false,
+        }
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    pub fn codec(&self) -> &C {
+        &self.codec
+    }
+
+    /// Returns the underlying connection, discarding any buffered bytes.
+    pub fn into_inner(self) -> T {
+        self.io
+    }
+}
+
+impl<T, C> Stream for Framed<T, C>
+where
+    T: AsyncRead + Unpin,
+    C: Codec + Unpin,
+{
+    type Item = Result<Value, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            let decoded = if this.eof {
+                this.codec.decode_eof(&mut this.read_buffer)
+            } else {
+                this.codec.decode(&mut this.read_buffer)
+            };
+
+            match decoded {
+                Ok(Some(message)) => return Poll::Ready(Some(Ok(message))),
+                Ok(None) if this.eof => return Poll::Ready(None),
+                Ok(None) => {}
+                Err(error @ CodecError::Json(_)) => return Poll::Ready(Some(Err(error.into()))),
+                Err(error) => {
+                    // Nothing after a broken frame can be trusted.
+                    this.read_buffer.clear();
+                    this.eof = true;
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+            }
+
+            let start = this.read_buffer.len();
+            this.read_buffer.resize(start + READ_CHUNK, 0);
+            let read = Pin::new(&mut this.io).poll_read(cx, &mut this.read_buffer[start..]);
+            let read = match read {
+                Poll::Ready(Ok(read)) => read,
+                Poll::Ready(Err(error)) => {
+                    this.read_buffer.truncate(start);
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+                Poll::Pending => {
+                    this.read_buffer.truncate(start);
+                    return Poll::Pending;
+                }
+            };
+
+            this.read_buffer.truncate(start + read);
+            if read == 0 {
+                this.eof = true;
+            }
+        }
+    }
+}
+
+impl<T, C> Framed<T, C>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), TransportError>> {
+        while !self.write_buffer.is_empty() {
+            let written = ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buffer))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into()));
+            }
+            self.write_buffer.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl<T, C> Sink<Value> for Framed<T, C>
+where
+    T: AsyncWrite + Unpin,
+    C: Codec + Unpin,
+{
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        if this.write_buffer.len() >= WRITE_HIGH_WATER_MARK {
+            ready!(this.poll_write_buffer(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Value) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        this.codec.encode(&item, &mut this.write_buffer)?;
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buffer(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.io).poll_flush(cx)).map_err(Into::into))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_buffer(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.io).poll_close(cx)).map_err(Into::into))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+    use futures::{SinkExt, StreamExt};
+    use serde_json::json;
+
+    #[test]
+    fn test_framed_reads_and_writes_frames() {
+        let input = b"Content-Length: 7\r\n\r\n{\"a\":1}Content-Length: 3\r\n\r\n[1]".to_vec();
+        let mut framed = Framed::new(Cursor::new(input), ContentLength::new());
+
+        assert_eq!(block_on(framed.next()).unwrap().unwrap(), json!({"a": 1}));
+        assert_eq!(block_on(framed.next()).unwrap().unwrap(), json!([1]));
+        assert!(block_on(framed.next()).is_none());
+
+        let mut output = Framed::new(Cursor::new(Vec::new()), ContentLength::new());
+        block_on(output.send(json!({"b": 2}))).unwrap();
+        assert_eq!(
+            output.into_inner().into_inner(),
+            b"Content-Length: 7\r\n\r\n{\"b\":2}"
+        );
+    }
+
+    #[test]
+    fn test_framed_recovers_from_invalid_json() {
+        let input = b"Content-Length: 3\r\n\r\n{]}Content-Length: 2\r\n\r\n{}".to_vec();
+        let mut framed = Framed::new(Cursor::new(input), ContentLength::new());
+
+        assert!(matches!(
+            block_on(framed.next()),
+            Some(Err(TransportError::Parse(_)))
+        ));
+        assert_eq!(block_on(framed.next()).unwrap().unwrap(), json!({}));
+    }
+
+    #[test]
+    fn test_framed_ends_after_invalid_frame() {
+        let input = b"Content-Length: x\r\n\r\n{}Content-Length: 2\r\n\r\n{}".to_vec();
+        let mut framed = Framed::new(Cursor::new(input), ContentLength::new());
+
+        assert!(matches!(
+            block_on(framed.next()),
+            Some(Err(TransportError::Io(error))) if error.kind() == io::ErrorKind::InvalidData
+        ));
+        assert!(block_on(framed.next()).is_none());
+    }
+
+    #[test]
+    fn test_framed_truncated_stream() {
+        let input = b"Content-Length: 10\r\n\r\n{}".to_vec();
+        let mut framed = Framed::new(Cursor::new(input), ContentLength::new());
+
+        assert!(matches!(
+            block_on(framed.next()),
+            Some(Err(TransportError::Io(_)))
+        ));
+        assert!(block_on(framed.next()).is_none());
+    }
+}
@@ -0,0 +1,317 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::Serialize;
+
+use super::{Codec, CodecError};
+
+/// Frames messages as JSON values written back to back, with no delimiter
+/// between them:
+///
+/// ```text
+/// {"jsonrpc":"2.0","id":1,"method":"ping"}[{"jsonrpc":"2.0","method":"tick"}]
+/// ```
+///
+/// Every message must be an object or, for a batch, an array. The end of a
+/// value is found by matching brackets outside of strings, so a value that
+/// is balanced but otherwise invalid, or longer than the maximum length, is
+/// reported as a [`CodecError::Json`] and skipped, while anything else at the
+/// top level breaks the stream.
+#[derive(Clone, Debug, Default)]
+pub struct Concatenated {
+    max_length: Option<usize>,
+    /// How far into the current value the buffer has been scanned already.
+    scanned: usize,
+    /// How many objects and arrays are open at that point.
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Whether the rest of an overlong value is being skipped.
+    discarding: bool,
+}
+
+impl Concatenated {
+    /// Creates a codec that accepts values of any length.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a codec that rejects values longer than `max_length` bytes.
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length: Some(max_length),
+            ..Self::default()
+        }
+    }
+
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    fn too_long(&self) -> CodecError {
+        let max_length = self.max_length.unwrap_or(usize::MAX);
+        CodecError::Json(serde_json::Error::custom(format!(
+            "value exceeds the maximum length of {max_length} bytes"
+        )))
+    }
+
+    /// Forgets the value being scanned.
+    fn reset(&mut self) {
+        *self = Self {
+            max_length: self.max_length,
+            ..Self::default()
+        };
+    }
+
+    /// Scans `src` for the end of the value it starts with, resuming where
+    /// the last call stopped. Returns the length of the value once complete.
+    fn scan(&mut self, src: &[u8]) -> Option<usize> {
+        for (index, byte) in src.iter().enumerate().skip(self.scanned) {
+            if self.in_string {
+                match byte {
+                    _ if self.escaped => self.escaped = false,
+                    b'\\' => self.escaped = true,
+                    b'"' => self.in_string = false,
+                    _ => {}
+                }
+                continue;
+            }
+
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' => {
+                    self.depth -= 1;
+                    if self.depth == 0 {
+                        self.scanned = 0;
+                        return Some(index + 1);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.scanned = src.len();
+        None
+    }
+}
+
+impl Codec for Concatenated {
+    fn encode<T>(&mut self, item: &T, dst: &mut Vec<u8>) -> Result<(), CodecError>
+    where
+        T: Serialize,
+    {
+        serde_json::to_writer(dst, item).map_err(CodecError::Json)
+    }
+
+    fn decode<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        if self.scanned == 0 && self.depth == 0 {
+            let start = src
+                .iter()
+                .position(|byte| !byte.is_ascii_whitespace())
+                .unwrap_or(src.len());
+            src.drain(..start);
+
+            match src.first() {
+                None => return Ok(None),
+                Some(b'{' | b'[') => {}
+                Some(byte) => {
+                    return Err(CodecError::InvalidFrame(format!(
+                        "expected an object or an array, found {:?}",
+                        char::from(*byte)
+                    )))
+                }
+            }
+        }
+
+        let max_length = self.max_length.unwrap_or(usize::MAX);
+        let Some(length) = self.scan(src) else {
+            // Only the state of the scan is kept while discarding.
+            if self.discarding || src.len() > max_length {
+                src.clear();
+                self.scanned = 0;
+                if !std::mem::replace(&mut self.discarding, true) {
+                    return Err(self.too_long());
+                }
+            }
+            return Ok(None);
+        };
+
+        let value: Vec<u8> = src.drain(..length).collect();
+        if std::mem::take(&mut self.discarding) {
+            return self.decode(src);
+        }
+        if length > max_length {
+            return Err(self.too_long());
+        }
+        serde_json::from_slice(&value)
+            .map(Some)
+            .map_err(CodecError::Json)
+    }
+
+    fn decode_eof<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() && self.depth == 0 => Ok(None),
+            None => {
+                // The rest of an overlong value was reported already.
+                let discarding = self.discarding;
+                self.reset();
+                src.clear();
+                if discarding {
+                    return Ok(None);
+                }
+                Err(CodecError::InvalidFrame(
+                    "stream ended in the middle of a frame".to_string(),
+                ))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, JsonRpcResponse, Message};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_concatenated_encode() {
+        let mut dst = Vec::new();
+        let mut codec = Concatenated::new();
+        codec.encode(&json!({"a": 1}), &mut dst).unwrap();
+        codec.encode(&json!([{"b": 2}]), &mut dst).unwrap();
+        assert_eq!(dst, br#"{"a":1}[{"b":2}]"#);
+    }
+
+    #[test]
+    fn test_concatenated_decode() {
+        let mut codec = Concatenated::new();
+        let mut src = br#" {"jsonrpc":"2.0","id":1,"result":19}
+            [{"jsonrpc":"2.0","method":"tick"}]{"jsonrpc""#
+            .to_vec();
+
+        let response: JsonRpcResponse<i64> = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), 19));
+
+        let batch: Message<Value, Value> = codec.decode(&mut src).unwrap().unwrap();
+        assert!(matches!(batch, Message::Batch(batch) if batch.len() == 1));
+
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        assert_eq!(src, br#"{"jsonrpc""#);
+    }
+
+    #[test]
+    fn test_concatenated_values_split_across_reads() {
+        let mut codec = Concatenated::new();
+        let input = br#"{"method":"echo","params":["}]", "\"{", "\\"],"id":1}{"id":2}"#;
+
+        let mut src = Vec::new();
+        let mut decoded = Vec::new();
+        for byte in input {
+            src.push(*byte);
+            if let Some(value) = codec.decode::<Value>(&mut src).unwrap() {
+                decoded.push(value);
+            }
+        }
+
+        assert_eq!(
+            decoded,
+            vec![
+                json!({"method": "echo", "params": ["}]", "\"{", "\\"], "id": 1}),
+                json!({"id": 2}),
+            ]
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_concatenated_skips_invalid_value() {
+        let mut codec = Concatenated::new();
+        let mut src = br#"{"a":]{"b":2}"#.to_vec();
+
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::Json(_))
+        ));
+        assert_eq!(
+            codec.decode::<Value>(&mut src).unwrap(),
+            Some(json!({"b": 2}))
+        );
+    }
+
+    #[test]
+    fn test_concatenated_max_length() {
+        let mut codec = Concatenated::with_max_length(8);
+        assert_eq!(codec.max_length(), Some(8));
+
+        // Too long, but complete.
+        let mut src = b"[1,2,3,4,5][1]".to_vec();
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::Json(_))
+        ));
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!([1])));
+
+        // Too long before the value has even ended, with brackets in a
+        // string of the skipped part.
+        let mut src = br#"{"a":"{"#.to_vec();
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        src.extend(b"[[[[");
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::Json(_))
+        ));
+        assert!(src.is_empty());
+        src.extend(br#"]]]]}","b":{}"#);
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        src.extend(b"}[2]");
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!([2])));
+    }
+
+    #[test]
+    fn test_concatenated_rejects_scalars() {
+        let mut codec = Concatenated::new();
+        let mut src = br#"42{"a":1}"#.to_vec();
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::InvalidFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_concatenated_decode_eof() {
+        let mut codec = Concatenated::new();
+
+        let mut src = b"{} \n".to_vec();
+        assert_eq!(
+            codec.decode_eof::<Value>(&mut src).unwrap(),
+            Some(json!({}))
+        );
+        assert!(codec.decode_eof::<Value>(&mut src).unwrap().is_none());
+
+        let mut src = br#"{"a":"#.to_vec();
+        assert!(matches!(
+            codec.decode_eof::<Value>(&mut src),
+            Err(CodecError::InvalidFrame(_))
+        ));
+
+        // Nothing of the truncated value is left to confuse the next one.
+        let mut src = br#"{"b":"}"}"#.to_vec();
+        assert_eq!(
+            codec.decode::<Value>(&mut src).unwrap(),
+            Some(json!({"b": "}"}))
+        );
+
+        let mut codec = Concatenated::with_max_length(4);
+        let mut src = br#"{"a":["#.to_vec();
+        assert!(codec.decode::<Value>(&mut src).is_err());
+        assert!(codec.decode_eof::<Value>(&mut src).unwrap().is_none());
+        let mut src = b"[1]".to_vec();
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!([1])));
+    }
+}
@@ -0,0 +1,306 @@
+use std::io::Write;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use super::{Codec, CodecError};
+
+const HEADER_END: &[u8] = b"\r\n\r\n";
+const MAX_HEADER_LENGTH: usize = 8 * 1024;
+
+/// Frames each message with a `Content-Length` header, as the Language
+/// Server and Debug Adapter protocols do:
+///
+/// ```text
+/// Content-Length: 52\r\n
+/// \r\n
+/// {"jsonrpc":"2.0","id":1,"method":"initialize"}
+/// ```
+///
+/// A `Content-Type` header is accepted as long as its charset is UTF-8, and
+/// other headers are ignored. Header names are case-insensitive.
+#[derive(Clone, Debug, Default)]
+pub struct ContentLength {
+    max_length: Option<usize>,
+    /// The length of the content whose header has been read already.
+    length: Option<usize>,
+}
+
+impl ContentLength {
+    /// Creates a codec that accepts content of any length.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a codec that rejects frames whose `Content-Length` exceeds
+    /// `max_length` bytes, before buffering their content.
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length: Some(max_length),
+            ..Self::default()
+        }
+    }
+
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    /// Reads the header block at the start of `src`, returning the content
+    /// length and the length of the block itself.
+    fn decode_header(src: &[u8]) -> Result<Option<(usize, usize)>, CodecError> {
+        let Some(end) = src.windows(HEADER_END.len()).position(|w| w == HEADER_END) else {
+            if src.len() > MAX_HEADER_LENGTH {
+                return Err(invalid("header is too long"));
+            }
+            return Ok(None);
+        };
+
+        let header =
+            std::str::from_utf8(&src[..end]).map_err(|_| invalid("header is not ASCII"))?;
+        let mut length = None;
+        for line in header.split("\r\n") {
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| invalid(format!("malformed header line {line:?}")))?;
+            let value = value.trim();
+
+            if name.eq_ignore_ascii_case("Content-Length") {
+                let parsed = value
+                    .parse::<usize>()
+                    .map_err(|_| invalid(format!("invalid Content-Length {value:?}")))?;
+                if length.is_some_and(|length| length != parsed) {
+                    return Err(invalid("conflicting Content-Length headers"));
+                }
+                length = Some(parsed);
+            } else if name.eq_ignore_ascii_case("Content-Type") && !is_utf8(value) {
+                return Err(invalid(format!("unsupported Content-Type {value:?}")));
+            }
+        }
+
+        let length = length.ok_or_else(|| invalid("missing Content-Length header"))?;
+        Ok(Some((length, end + HEADER_END.len())))
+    }
+}
+
+impl Codec for ContentLength {
+    fn encode<T>(&mut self, item: &T, dst: &mut Vec<u8>) -> Result<(), CodecError>
+    where
+        T: Serialize,
+    {
+        let content = serde_json::to_vec(item).map_err(CodecError::Json)?;
+        write!(dst, "Content-Length: {}\r\n\r\n", content.len())
+            .expect("writing to a Vec never fails");
+        dst.extend_from_slice(&content);
+        Ok(())
+    }
+
+    fn decode<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        let length = match self.length {
+            Some(length) => length,
+            None => match Self::decode_header(src)? {
+                Some((length, _)) if self.max_length.is_some_and(|max| length > max) => {
+                    return Err(invalid(format!(
+                        "Content-Length {length} exceeds the maximum length of {} bytes",
+                        self.max_length.unwrap_or(usize::MAX)
+                    )));
+                }
+                Some((length, header_length)) => {
+                    src.drain(..header_length);
+                    self.length = Some(length);
+                    length
+                }
+                None => return Ok(None),
+            },
+        };
+
+        if src.len() < length {
+            return Ok(None);
+        }
+
+        self.length = None;
+        let content: Vec<u8> = src.drain(..length).collect();
+        serde_json::from_slice(&content)
+            .map(Some)
+            .map_err(CodecError::Json)
+    }
+
+    fn decode_eof<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        match self.decode(src)? {
+            Some(item) => Ok(Some(item)),
+            None if src.is_empty() && self.length.is_none() => Ok(None),
+            None => {
+                src.clear();
+                self.length = None;
+                Err(invalid("stream ended in the middle of a frame"))
+            }
+        }
+    }
+}
+
+/// Whether a `Content-Type` header value denotes UTF-8 content, which it does
+/// when it doesn't name a charset.
+fn is_utf8(content_type: &str) -> bool {
+    content_type
+        .split(';')
+        .skip(1)
+        .filter_map(|parameter| parameter.split_once('='))
+        .filter(|(name, _)| name.trim().eq_ignore_ascii_case("charset"))
+        .all(|(_, charset)| {
+            let charset = charset.trim().trim_matches('"');
+            charset.eq_ignore_ascii_case("utf-8") || charset.eq_ignore_ascii_case("utf8")
+        })
+}
+
+fn invalid(reason: impl Into<String>) -> CodecError {
+    CodecError::InvalidFrame(reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, JsonRpcRequest, JsonRpcResponse, Request};
+    use serde_json::{json, Value};
+
+    fn frame(content: &str) -> Vec<u8> {
+        format!("Content-Length: {}\r\n\r\n{content}", content.len()).into_bytes()
+    }
+
+    #[test]
+    fn test_content_length_encode() {
+        let request = JsonRpcRequest {
+            header: Header::v2(1),
+            payload: Request::<Value>::without_params("initialize"),
+        };
+
+        let mut dst = Vec::new();
+        ContentLength::new().encode(&request, &mut dst).unwrap();
+        assert_eq!(
+            dst,
+            frame(r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#)
+        );
+    }
+
+    #[test]
+    fn test_content_length_decode() {
+        let mut codec = ContentLength::new();
+        let mut src = frame(r#"{"jsonrpc":"2.0","id":1,"result":19}"#);
+        src.extend(frame(r#"{"jsonrpc":"2.0","id":2,"method":"exit"}"#));
+
+        let response: JsonRpcResponse<i64> = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), 19));
+
+        let request: JsonRpcRequest<Request> = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(request.payload.method, "exit");
+
+        assert!(src.is_empty());
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_content_length_partial_reads() {
+        let mut codec = ContentLength::new();
+        let input = frame(r#"{"jsonrpc":"2.0","id":1,"result":19}"#);
+
+        let mut src = Vec::new();
+        let (last, rest) = input.split_last().unwrap();
+        for byte in rest {
+            src.push(*byte);
+            assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        }
+
+        src.push(*last);
+        assert_eq!(
+            codec.decode::<Value>(&mut src).unwrap(),
+            Some(json!({"jsonrpc": "2.0", "id": 1, "result": 19}))
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_content_length_headers() {
+        let mut codec = ContentLength::new();
+
+        let mut src = b"content-length: 2\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\nX-Other: 1\r\n\r\n{}".to_vec();
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!({})));
+
+        let mut src = b"Content-Type: application/json\r\nContent-Length:2\r\n\r\n[]".to_vec();
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!([])));
+    }
+
+    #[test]
+    fn test_content_length_malformed_headers() {
+        let cases: [&[u8]; 6] = [
+            b"Content-Type: application/json\r\n\r\n{}",
+            b"Content-Length: -1\r\n\r\n{}",
+            b"Content-Length: 2\r\nContent-Length: 3\r\n\r\n{}",
+            b"Content-Length 2\r\n\r\n{}",
+            b"Content-Length: 2\r\nContent-Type: text/plain; charset=latin1\r\n\r\n{}",
+            b"Content-Length: \xff\r\n\r\n{}",
+        ];
+
+        for case in cases {
+            let result = ContentLength::new().decode::<Value>(&mut case.to_vec());
+            assert!(
+                matches!(result, Err(CodecError::InvalidFrame(_))),
+                "{:?}",
+                String::from_utf8_lossy(case)
+            );
+        }
+
+        let mut src = vec![b'a'; MAX_HEADER_LENGTH + 1];
+        assert!(matches!(
+            ContentLength::new().decode::<Value>(&mut src),
+            Err(CodecError::InvalidFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_content_length_max_length() {
+        let mut codec = ContentLength::with_max_length(2);
+        assert_eq!(codec.max_length(), Some(2));
+        assert_eq!(
+            codec.decode::<Value>(&mut frame("{}")).unwrap(),
+            Some(json!({}))
+        );
+
+        // Rejected on the header alone, without waiting for the content.
+        let mut src = b"Content-Length: 99999999999\r\n\r\n".to_vec();
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::InvalidFrame(_))
+        ));
+    }
+
+    #[test]
+    fn test_content_length_invalid_content() {
+        let mut codec = ContentLength::new();
+        let mut src = frame("{]");
+        src.extend(frame("{}"));
+
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::Json(_))
+        ));
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!({})));
+    }
+
+    #[test]
+    fn test_content_length_decode_eof() {
+        let mut codec = ContentLength::new();
+        let mut src = b"Content-Length: 4\r\n\r\n{}".to_vec();
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        assert!(matches!(
+            codec.decode_eof::<Value>(&mut src),
+            Err(CodecError::InvalidFrame(_))
+        ));
+
+        let mut src = Vec::new();
+        assert!(codec.decode_eof::<Value>(&mut src).unwrap().is_none());
+    }
+}
@@ -0,0 +1,263 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::Serialize;
+
+use super::{Codec, CodecError};
+
+/// Frames each message as a single line of JSON, terminated by `\n`.
+///
+/// Blank lines are skipped and a trailing `\r` is ignored. A line that isn't
+/// valid JSON, or that is longer than the maximum length, is reported as a
+/// [`CodecError::Json`] and skipped, so decoding carries on with the next
+/// line.
+#[derive(Clone, Debug, Default)]
+pub struct Ndjson {
+    max_length: Option<usize>,
+    /// How far the buffer has been searched for a newline already.
+    searched: usize,
+    /// Whether the rest of an overlong line is being skipped.
+    discarding: bool,
+}
+
+impl Ndjson {
+    /// Creates a codec that accepts lines of any length.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a codec that rejects lines longer than `max_length` bytes,
+    /// not counting the newline.
+    pub fn with_max_length(max_length: usize) -> Self {
+        Self {
+            max_length: Some(max_length),
+            ..Self::default()
+        }
+    }
+
+    pub fn max_length(&self) -> Option<usize> {
+        self.max_length
+    }
+
+    fn too_long(&self) -> CodecError {
+        let max_length = self.max_length.unwrap_or(usize::MAX);
+        CodecError::Json(serde_json::Error::custom(format!(
+            "line exceeds the maximum length of {max_length} bytes"
+        )))
+    }
+}
+
+impl Codec for Ndjson {
+    fn encode<T>(&mut self, item: &T, dst: &mut Vec<u8>) -> Result<(), CodecError>
+    where
+        T: Serialize,
+    {
+        // Compact JSON never contains a raw newline.
+        serde_json::to_writer(&mut *dst, item).map_err(CodecError::Json)?;
+        dst.push(b'\n');
+        Ok(())
+    }
+
+    fn decode<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        loop {
+            let newline = src[self.searched..]
+                .iter()
+                .position(|byte| *byte == b'\n')
+                .map(|position| self.searched + position);
+
+            let Some(newline) = newline else {
+                if self.discarding {
+                    src.clear();
+                    self.searched = 0;
+                } else if self.max_length.is_some_and(|max| src.len() > max) {
+                    src.clear();
+                    self.searched = 0;
+                    self.discarding = true;
+                    return Err(self.too_long());
+                } else {
+                    self.searched = src.len();
+                }
+                return Ok(None);
+            };
+
+            let line: Vec<u8> = src.drain(..=newline).collect();
+            self.searched = 0;
+            if std::mem::take(&mut self.discarding) {
+                continue;
+            }
+
+            if let Some(item) = self.decode_line(&line[..newline])? {
+                return Ok(Some(item));
+            }
+        }
+    }
+
+    fn decode_eof<T>(&mut self, src: &mut Vec<u8>) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        if let Some(item) = self.decode(src)? {
+            return Ok(Some(item));
+        }
+
+        // The last line may lack its newline.
+        let line = std::mem::take(src);
+        self.searched = 0;
+        if std::mem::take(&mut self.discarding) {
+            return Ok(None);
+        }
+        self.decode_line(&line)
+    }
+}
+
+impl Ndjson {
+    fn decode_line<T>(&self, line: &[u8]) -> Result<Option<T>, CodecError>
+    where
+        T: DeserializeOwned,
+    {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return Ok(None);
+        }
+        if self.max_length.is_some_and(|max| line.len() > max) {
+            return Err(self.too_long());
+        }
+        serde_json::from_slice(line)
+            .map(Some)
+            .map_err(CodecError::Json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, JsonRpcRequest, JsonRpcResponse, Request};
+    use serde_json::{json, Value};
+
+    fn decode_all(codec: &mut Ndjson, src: &mut Vec<u8>) -> Vec<Result<Value, String>> {
+        let mut decoded = Vec::new();
+        loop {
+            match codec.decode_eof(src) {
+                Ok(Some(value)) => decoded.push(Ok(value)),
+                Ok(None) => return decoded,
+                Err(error) => decoded.push(Err(error.to_string())),
+            }
+        }
+    }
+
+    #[test]
+    fn test_ndjson_encode() {
+        let request = JsonRpcRequest {
+            header: Header::v2(1),
+            payload: Request::new("echo", json!(["a\nb"])),
+        };
+
+        let mut dst = Vec::new();
+        let mut codec = Ndjson::new();
+        codec.encode(&request, &mut dst).unwrap();
+        codec.encode(&json!({}), &mut dst).unwrap();
+        assert_eq!(
+            dst,
+            b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"echo\",\"params\":[\"a\\nb\"]}\n{}\n"
+        );
+    }
+
+    #[test]
+    fn test_ndjson_decode() {
+        let mut codec = Ndjson::new();
+        let mut src = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"result\":19}\r\n\n  \n[1,".to_vec();
+
+        let response: JsonRpcResponse<i64> = codec.decode(&mut src).unwrap().unwrap();
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), 19));
+
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        src.extend(b"2]\n");
+        assert_eq!(
+            codec.decode::<Value>(&mut src).unwrap(),
+            Some(json!([1, 2]))
+        );
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn test_ndjson_decode_eof() {
+        let mut codec = Ndjson::new();
+        let mut src = b"{}\n[]".to_vec();
+        assert_eq!(
+            decode_all(&mut codec, &mut src),
+            vec![Ok(json!({})), Ok(json!([]))]
+        );
+    }
+
+    #[test]
+    fn test_ndjson_resynchronizes_after_malformed_line() {
+        let mut codec = Ndjson::new();
+        let mut src = b"{\"a\":1}\n{\"b\":\n{\"c\":3}\n".to_vec();
+
+        let decoded = decode_all(&mut codec, &mut src);
+        assert_eq!(decoded.len(), 3);
+        assert_eq!(decoded[0], Ok(json!({"a": 1})));
+        assert!(decoded[1].is_err());
+        assert_eq!(decoded[2], Ok(json!({"c": 3})));
+    }
+
+    #[test]
+    fn test_ndjson_max_length() {
+        let mut codec = Ndjson::with_max_length(8);
+        assert_eq!(codec.max_length(), Some(8));
+
+        // Too long, but complete.
+        let mut src = b"[1,2,3,4,5]\n[1]\n".to_vec();
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::Json(_))
+        ));
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!([1])));
+
+        // Too long before the newline has even arrived.
+        let mut src = b"[1,2,3,4,".to_vec();
+        assert!(matches!(
+            codec.decode::<Value>(&mut src),
+            Err(CodecError::Json(_))
+        ));
+        assert!(src.is_empty());
+        src.extend(b"5,6,7,8,9,10,11");
+        assert!(codec.decode::<Value>(&mut src).unwrap().is_none());
+        src.extend(b"]\n[2]\n");
+        assert_eq!(codec.decode::<Value>(&mut src).unwrap(), Some(json!([2])));
+    }
+
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_ndjson_server_answers_malformed_line_with_parse_error() {
+        use crate::codec::testing::Duplex;
+        use crate::codec::Framed;
+        use crate::{ErrorObject, Router, Server};
+        use futures::executor::block_on;
+
+        let mut router = Router::new();
+        router.register("ping", |_: ()| Ok::<_, ErrorObject>("pong"));
+        let server = Server::new(router);
+
+        let input = b"{\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":1\n\
+            {\"jsonrpc\":\"2.0\",\"method\":\"ping\",\"id\":2}\n"
+            .to_vec();
+        let io = Duplex::new(input);
+        let framed = Framed::new(io.clone(), Ndjson::new());
+        block_on(server.serve(framed)).unwrap();
+
+        let output = io.output();
+        let replies: Vec<Value> = serde_json::Deserializer::from_slice(&output)
+            .into_iter()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(
+            replies,
+            vec![
+                json!({"jsonrpc": "2.0", "id": null, "error": {"code": -32700, "message": "Parse error"}}),
+                json!({"jsonrpc": "2.0", "id": 2, "result": "pong"}),
+            ]
+        );
+    }
+}
@@ -0,0 +1,66 @@
+//! Fixtures shared by the tests of the transports built on byte streams.
+
+use std::io;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::io::{AsyncRead, AsyncWrite, Cursor};
+
+/// Reads from a fixed input and collects everything written, until it is
+/// broken. Clones share what is written, so it can still be read once the
+/// duplex has been moved into a transport.
+#[derive(Clone)]
+pub(crate) struct Duplex {
+    input: Cursor<Vec<u8>>,
+    output: Arc<Mutex<Vec<u8>>>,
+    /// Whether writes fail, as to a peer that went away.
+    pub(crate) broken: bool,
+}
+
+impl Duplex {
+    pub(crate) fn new(input: Vec<u8>) -> Self {
+        Self {
+            input: Cursor::new(input),
+            output: Arc::default(),
+            broken: false,
+        }
+    }
+
+    /// Everything written so far.
+    pub(crate) fn output(&self) -> Vec<u8> {
+        self.output.lock().unwrap().clone()
+    }
+}
+
+impl AsyncRead for Duplex {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut [u8],
+    ) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.input).poll_read(cx, buf)
+    }
+}
+
+impl AsyncWrite for Duplex {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        _: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        if self.broken {
+            return Poll::Ready(Err(io::ErrorKind::BrokenPipe.into()));
+        }
+        self.output.lock().unwrap().extend_from_slice(buf);
+        Poll::Ready(Ok(buf.len()))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt;
+
+/// Error codes reserved by the JSON-RPC 2.0 specification.
+pub mod error_code {
+    /// Invalid JSON was received by the server.
+    pub const PARSE_ERROR: i64 = -32700;
+    /// The JSON sent is not a valid Request object.
+    pub const INVALID_REQUEST: i64 = -32600;
+    /// The method does not exist or is not available.
+    pub const METHOD_NOT_FOUND: i64 = -32601;
+    /// Invalid method parameter(s).
+    pub const INVALID_PARAMS: i64 = -32602;
+    /// Internal JSON-RPC error.
+    pub const INTERNAL_ERROR: i64 = -32603;
+    /// Lower bound of the range reserved for implementation-defined server errors.
+    pub const SERVER_ERROR_START: i64 = -32099;
+    /// Upper bound of the range reserved for implementation-defined server errors.
+    pub const SERVER_ERROR_END: i64 = -32000;
+}
+
+/// The `error` member of a JSON-RPC response.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct ErrorObject<D = serde_json::Value> {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<D>,
+}
+
+impl<D> ErrorObject<D> {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data: None,
+        }
+    }
+
+    pub fn parse_error() -> Self {
+        Self::new(error_code::PARSE_ERROR, "Parse error")
+    }
+
+    pub fn invalid_request() -> Self {
+        Self::new(error_code::INVALID_REQUEST, "Invalid Request")
+    }
+
+    pub fn method_not_found() -> Self {
+        Self::new(error_code::METHOD_NOT_FOUND, "Method not found")
+    }
+
+    pub fn invalid_params() -> Self {
+        Self::new(error_code::INVALID_PARAMS, "Invalid params")
+    }
+
+    pub fn internal_error() -> Self {
+        Self::new(error_code::INTERNAL_ERROR, "Internal error")
+    }
+
+    /// Builds an implementation-defined server error, returning `None` when
+    /// `code` falls outside of the reserved `-32099..=-32000` range.
+    pub fn server_error(code: i64, message: impl Into<String>) -> Option<Self> {
+        Self::is_server_error_code(code).then(|| Self::new(code, message))
+    }
+
+    pub fn with_data(mut self, data: D) -> Self {
+        self.data = Some(data);
+        self
+    }
+
+    pub fn is_server_error(&self) -> bool {
+        Self::is_server_error_code(self.code)
+    }
+
+    fn is_server_error_code(code: i64) -> bool {
+        (error_code::SERVER_ERROR_START..=error_code::SERVER_ERROR_END).contains(&code)
+    }
+}
+
+impl<D> fmt::Display for ErrorObject<D> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({})", self.message, self.code)
+    }
+}
+
+impl<D> std::error::Error for ErrorObject<D> where D: fmt::Debug {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_reserved_error_constructors() {
+        let cases: [(ErrorObject, i64, &str); 5] = [
+            (ErrorObject::parse_error(), -32700, "Parse error"),
+            (ErrorObject::invalid_request(), -32600, "Invalid Request"),
+            (ErrorObject::method_not_found(), -32601, "Method not found"),
+            (ErrorObject::invalid_params(), -32602, "Invalid params"),
+            (ErrorObject::internal_error(), -32603, "Internal error"),
+        ];
+
+        for (error, code, message) in cases {
+            assert_eq!(error.code, code);
+            assert_eq!(error.message, message);
+            assert_eq!(error.data, None);
+            assert!(!error.is_server_error());
+        }
+    }
+
+    #[test]
+    fn test_server_error_range() {
+        let error = ErrorObject::<()>::server_error(-32000, "Busy").unwrap();
+        assert_eq!(error.code, -32000);
+        assert!(error.is_server_error());
+
+        assert!(ErrorObject::<()>::server_error(-32099, "Busy").is_some());
+        assert!(ErrorObject::<()>::server_error(-31999, "Busy").is_none());
+        assert!(ErrorObject::<()>::server_error(-32100, "Busy").is_none());
+        assert!(ErrorObject::<()>::server_error(-32601, "Busy").is_none());
+    }
+
+    #[test]
+    fn test_error_object_serialization() {
+        let error: ErrorObject = ErrorObject::method_not_found();
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json, json!({"code": -32601, "message": "Method not found"}));
+
+        let error = ErrorObject::invalid_params().with_data(json!({"field": "name"}));
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "code": -32602,
+                "message": "Invalid params",
+                "data": {"field": "name"}
+            })
+        );
+    }
+
+    #[test]
+    fn test_error_object_typed_data() {
+        #[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Details {
+            retry_after: u32,
+        }
+
+        let json = json!({"code": -32001, "message": "Busy", "data": {"retry_after": 5}});
+        let error: ErrorObject<Details> = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            error,
+            ErrorObject::server_error(-32001, "Busy")
+                .unwrap()
+                .with_data(Details { retry_after: 5 })
+        );
+
+        let json = json!({"code": -32001, "message": "Busy"});
+        let error: ErrorObject<Details> = serde_json::from_value(json).unwrap();
+        assert_eq!(error.data, None);
+
+        let json = json!({"code": -32001, "message": "Busy", "data": "oops"});
+        assert!(serde_json::from_value::<ErrorObject<Details>>(json).is_err());
+    }
+
+    #[test]
+    fn test_error_object_display() {
+        let error: ErrorObject<Value> = ErrorObject::internal_error();
+        assert_eq!(error.to_string(), "Internal error (-32603)");
+    }
+}
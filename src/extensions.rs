@@ -0,0 +1,85 @@
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+/// A map of values keyed by their type, for whatever a transport knows about
+/// a connection, such as the address or credentials of the peer.
+///
+/// Cloning is cheap: the values themselves are shared.
+#[derive(Clone, Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Arc<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Inserts `value`, replacing any previous value of the same type.
+    pub fn insert<T>(&mut self, value: T) -> &mut Self
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.insert(TypeId::of::<T>(), Arc::new(value));
+        self
+    }
+
+    pub fn get<T>(&self) -> Option<&T>
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    pub fn contains<T>(&self) -> bool
+    where
+        T: Send + Sync + 'static,
+    {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+}
+
+impl fmt::Debug for Extensions {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    struct Peer(&'static str);
+
+    #[test]
+    fn test_extensions() {
+        let mut extensions = Extensions::new();
+        assert!(extensions.is_empty());
+
+        extensions.insert(Peer("a")).insert(7u32);
+        assert_eq!(extensions.get::<Peer>(), Some(&Peer("a")));
+        assert_eq!(extensions.get::<u32>(), Some(&7));
+        assert!(!extensions.contains::<u64>());
+
+        let cloned = extensions.clone();
+        extensions.insert(Peer("b"));
+        assert_eq!(extensions.len(), 2);
+        assert_eq!(extensions.get::<Peer>(), Some(&Peer("b")));
+        assert_eq!(cloned.get::<Peer>(), Some(&Peer("a")));
+    }
+}
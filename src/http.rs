@@ -0,0 +1,260 @@
+//! JSON-RPC over HTTP: each request is the body of a `POST`, and each reply
+//! the body of its response.
+
+use std::io;
+
+use futures::io::{AsyncRead, AsyncReadExt};
+
+#[cfg(feature = "http-client")]
+mod client;
+#[cfg(feature = "http")]
+mod server;
+
+#[cfg(feature = "http-client")]
+pub use client::{HttpError, HttpTransport};
+#[cfg(feature = "http")]
+pub use server::HttpServer;
+
+/// The largest body accepted unless configured otherwise: 1 MiB.
+#[cfg(any(feature = "http", feature = "http-client"))]
+pub const DEFAULT_MAX_BODY_LENGTH: usize = 1024 * 1024;
+
+const MAX_HEAD_LENGTH: usize = 8 * 1024;
+const HEAD_END: &[u8] = b"\r\n\r\n";
+const READ_CHUNK: usize = 8 * 1024;
+
+/// The start line and headers of an HTTP message.
+#[derive(Debug)]
+pub(crate) struct Head {
+    pub(crate) start_line: String,
+    headers: Vec<(String, String)>,
+}
+
+impl Head {
+    fn parse(bytes: &[u8]) -> io::Result<Self> {
+        let head = std::str::from_utf8(bytes).map_err(|_| invalid("head is not ASCII"))?;
+        let mut lines = head.split("\r\n");
+        let start_line = lines.next().unwrap_or_default().to_string();
+
+        let headers = lines
+            .map(|line| {
+                line.split_once(':')
+                    .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                    .ok_or_else(|| invalid(format!("malformed header line {line:?}")))
+            })
+            .collect::<io::Result<_>>()?;
+
+        Ok(Self {
+            start_line,
+            headers,
+        })
+    }
+
+    /// The value of the header `name`, compared case-insensitively.
+    pub(crate) fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(header, _)| header.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The length announced by `Content-Length`; `Ok(None)` when absent.
+    #[cfg(any(feature = "http", feature = "http-client"))]
+    fn content_length(&self) -> io::Result<Option<usize>> {
+        self.header("Content-Length")
+            .map(|value| {
+                value
+                    .parse()
+                    .map_err(|_| invalid(format!("invalid Content-Length {value:?}")))
+            })
+            .transpose()
+    }
+
+    /// Whether the header `name` lists `token`, as in `Connection: close`.
+    pub(crate) fn has_token(&self, name: &str, token: &str) -> bool {
+        self.headers
+            .iter()
+            .filter(|(header, _)| header.eq_ignore_ascii_case(name))
+            .flat_map(|(_, value)| value.split(','))
+            .any(|value| value.trim().eq_ignore_ascii_case(token))
+    }
+}
+
+/// Reads the head of the next message, keeping whatever follows it in
+/// `buffer`. Returns `None` if the stream ends before the message starts.
+pub(crate) async fn read_head<R>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<Option<Head>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut searched = 0;
+    loop {
+        if let Some(end) = buffer[searched..]
+            .windows(HEAD_END.len())
+            .position(|window| window == HEAD_END)
+        {
+            let end = searched + end;
+            let head = Head::parse(&buffer[..end]);
+            buffer.drain(..end + HEAD_END.len());
+            return head.map(Some);
+        }
+
+        if buffer.len() > MAX_HEAD_LENGTH {
+            return Err(invalid("head is too long"));
+        }
+        searched = buffer.len().saturating_sub(HEAD_END.len() - 1);
+
+        if read_more(reader, buffer).await? == 0 {
+            if buffer.is_empty() {
+                return Ok(None);
+            }
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+}
+
+/// Reads a body of `length` bytes, starting with those already in `buffer`.
+#[cfg(any(feature = "http", feature = "http-client"))]
+async fn read_body<R>(reader: &mut R, buffer: &mut Vec<u8>, length: usize) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    while buffer.len() < length {
+        if read_more(reader, buffer).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+    Ok(buffer.drain(..length).collect())
+}
+
+async fn read_more<R>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<usize>
+where
+    R: AsyncRead + Unpin,
+{
+    let start = buffer.len();
+    buffer.resize(start + READ_CHUNK, 0);
+    let read = reader.read(&mut buffer[start..]).await;
+    buffer.truncate(start + *read.as_ref().unwrap_or(&0));
+    read
+}
+
+/// Whether a `Content-Type` denotes JSON. The JSON-RPC specific media types
+/// some peers use are accepted too.
+#[cfg(any(feature = "http", feature = "http-client"))]
+fn is_json(content_type: &str) -> bool {
+    let media_type = content_type.split(';').next().unwrap_or_default().trim();
+    [
+        "application/json",
+        "application/json-rpc",
+        "application/jsonrequest",
+    ]
+    .iter()
+    .any(|json| media_type.eq_ignore_ascii_case(json))
+}
+
+/// The parts of an `http://` or `ws://` URL that a client needs.
+#[cfg(any(feature = "http-client", feature = "websocket"))]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Url {
+    /// The `host:port` to connect to.
+    pub(crate) addr: String,
+    /// The `Host` header: the host and port as written in the URL.
+    pub(crate) host: String,
+    pub(crate) path: String,
+}
+
+#[cfg(any(feature = "http-client", feature = "websocket"))]
+impl Url {
+    /// Parses `url`, which must start with `scheme://`. The port defaults to
+    /// 80, as neither TLS nor its schemes are supported.
+    pub(crate) fn parse(url: &str, scheme: &str) -> Option<Self> {
+        let rest = url.strip_prefix(scheme)?.strip_prefix("://")?;
+        let (host, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, "/"),
+        };
+        if host.is_empty() || host.contains('@') {
+            return None;
+        }
+
+        // The port follows the last colon, unless that colon is within an
+        // IPv6 address.
+        let has_port = host
+            .rsplit_once(':')
+            .is_some_and(|(_, port)| !port.contains(']'));
+        let addr = if has_port {
+            host.to_string()
+        } else {
+            format!("{host}:80")
+        };
+
+        Some(Self {
+            addr,
+            host: host.to_string(),
+            path: path.to_string(),
+        })
+    }
+}
+
+pub(crate) fn invalid(reason: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, reason.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::io::Cursor;
+
+    #[cfg(any(feature = "http", feature = "http-client"))]
+    #[test]
+    fn test_read_head() {
+        let mut reader = Cursor::new(
+            b"POST / HTTP/1.1\r\nContent-Length: 2\r\nConnection: keep-alive, Close\r\n\r\n{}"
+                .to_vec(),
+        );
+        let mut buffer = Vec::new();
+
+        let head = block_on(read_head(&mut reader, &mut buffer))
+            .unwrap()
+            .unwrap();
+        assert_eq!(head.start_line, "POST / HTTP/1.1");
+        assert_eq!(head.header("content-length"), Some("2"));
+        assert_eq!(head.content_length().unwrap(), Some(2));
+        assert!(head.has_token("Connection", "close"));
+        assert!(!head.has_token("Connection", "upgrade"));
+
+        let body = block_on(read_body(&mut reader, &mut buffer, 2)).unwrap();
+        assert_eq!(body, b"{}");
+        assert!(block_on(read_head(&mut reader, &mut buffer))
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_read_head_errors() {
+        let cases: [&[u8]; 3] = [
+            b"POST / HTTP/1.1\r\nno colon\r\n\r\n",
+            b"POST / HTTP/1.1\r\nContent-Length: 2\r\n",
+            b"POST / HTTP/1.1\r\n\xff: 1\r\n\r\n",
+        ];
+
+        for case in cases {
+            let mut reader = Cursor::new(case.to_vec());
+            assert!(block_on(read_head(&mut reader, &mut Vec::new())).is_err());
+        }
+
+        let mut reader = Cursor::new(vec![b'a'; MAX_HEAD_LENGTH * 2]);
+        let error = block_on(read_head(&mut reader, &mut Vec::new())).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[cfg(any(feature = "http", feature = "http-client"))]
+    #[test]
+    fn test_is_json() {
+        assert!(is_json("application/json"));
+        assert!(is_json("Application/JSON; charset=utf-8"));
+        assert!(is_json("application/json-rpc"));
+        assert!(!is_json("text/plain"));
+        assert!(!is_json("application/jsonp"));
+    }
+}
@@ -0,0 +1,746 @@
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll, Waker};
+
+use futures::future::BoxFuture;
+use futures::io::{AsyncRead, AsyncWriteExt};
+use futures::stream::FuturesUnordered;
+use futures::{Sink, Stream, StreamExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use super::{
+    invalid, is_json, read_body, read_head, read_more, Head, Url, DEFAULT_MAX_BODY_LENGTH,
+};
+use crate::notification::is_notification;
+use crate::tcp;
+use crate::transport::TransportError;
+use crate::{Batch, Call, Id, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse};
+
+/// Errors raised by an [`HttpTransport`], as opposed to the error objects a
+/// server answers with, which are part of the responses.
+#[derive(Debug)]
+pub enum HttpError {
+    /// The URL isn't a valid `http://` URL.
+    InvalidUrl(String),
+    /// The connection failed, or the response isn't valid HTTP.
+    Io(io::Error),
+    /// The server answered with a status other than 2xx.
+    Status { status: u16, reason: String },
+    /// The response has no body, though the request expected replies.
+    EmptyBody,
+    /// The response body isn't JSON, according to its `Content-Type`.
+    ContentType(Option<String>),
+    /// The response body isn't what the request expected.
+    Json(serde_json::Error),
+}
+
+impl fmt::Display for HttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidUrl(url) => write!(f, "invalid URL {url:?}"),
+            Self::Io(error) => write!(f, "HTTP request failed: {error}"),
+            Self::Status { status, reason } => write!(f, "HTTP status {status} {reason}"),
+            Self::EmptyBody => f.write_str("HTTP response has no body"),
+            Self::ContentType(Some(content_type)) => {
+                write!(f, "unexpected HTTP content type {content_type:?}")
+            }
+            Self::ContentType(None) => f.write_str("HTTP response has no content type"),
+            Self::Json(error) => write!(f, "failed to deserialize HTTP response: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for HttpError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io(error) => Some(error),
+            Self::Json(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for HttpError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// Sends JSON-RPC messages as HTTP `POST` requests to a URL, one connection
+/// per request.
+///
+/// Messages can be posted one at a time through [`HttpTransport::request`]
+/// and its siblings, or as a [`Transport`](crate::transport::Transport), so
+/// that a [`Client`](crate::Client) can run over HTTP: every message sent
+/// through the sink is posted, and the reply in the response body, if any,
+/// is yielded by the stream. Posts are made concurrently, as the stream is
+/// polled. One that fails doesn't end the stream: it yields a
+/// [`TransportError::Unanswered`] with the ids of the requests it carried,
+/// so that a client fails only those calls.
+///
+/// Only plain `http://` URLs are supported.
+#[derive(Debug)]
+pub struct HttpTransport {
+    url: Url,
+    max_body_length: usize,
+    in_flight: FuturesUnordered<BoxFuture<'static, Result<Option<Value>, TransportError>>>,
+    /// The task waiting for posts to be made.
+    waker: Option<Waker>,
+    closed: bool,
+}
+
+impl Clone for HttpTransport {
+    /// Returns a transport posting to the same URL, without the posts still
+    /// in flight.
+    fn clone(&self) -> Self {
+        Self {
+            url: self.url.clone(),
+            max_body_length: self.max_body_length,
+            in_flight: FuturesUnordered::new(),
+            waker: None,
+            closed: false,
+        }
+    }
+}
+
+impl HttpTransport {
+    pub fn new(url: &str) -> Result<Self, HttpError> {
+        let url = Url::parse(url, "http").ok_or_else(|| HttpError::InvalidUrl(url.to_string()))?;
+        Ok(Self {
+            url,
+            max_body_length: DEFAULT_MAX_BODY_LENGTH,
+            in_flight: FuturesUnordered::new(),
+            waker: None,
+            closed: false,
+        })
+    }
+
+    /// Fails responses whose body is longer than `max_body_length` bytes.
+    pub fn with_max_body_length(mut self, max_body_length: usize) -> Self {
+        self.max_body_length = max_body_length;
+        self
+    }
+
+    pub fn max_body_length(&self) -> usize {
+        self.max_body_length
+    }
+
+    /// Sends `request` and waits for its response.
+    pub async fn request<P, R, E>(
+        &self,
+        request: &JsonRpcRequest<P>,
+    ) -> Result<JsonRpcResponse<R, E>, HttpError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        self.post(request, true).await.map(Option::unwrap)
+    }
+
+    /// Sends `notification`, expecting no reply.
+    pub async fn notify<P>(&self, notification: &JsonRpcNotification<P>) -> Result<(), HttpError>
+    where
+        P: Serialize,
+    {
+        self.post::<_, ()>(notification, false).await.map(drop)
+    }
+
+    /// Sends `batch` and waits for the responses to its requests, in the
+    /// order the server sent them. A batch of notifications gets none.
+    pub async fn batch<P, R, E>(
+        &self,
+        batch: &Batch<Call<P>>,
+    ) -> Result<Vec<JsonRpcResponse<R, E>>, HttpError>
+    where
+        P: Serialize,
+        R: DeserializeOwned,
+        E: DeserializeOwned,
+    {
+        let responses = self.post(batch, batch.expects_response()).await?;
+        Ok(responses.unwrap_or_default())
+    }
+
+    /// Posts `message`, returning the decoded body if `expects_reply`.
+    async fn post<T, R>(&self, message: &T, expects_reply: bool) -> Result<Option<R>, HttpError>
+    where
+        T: Serialize,
+        R: DeserializeOwned,
+    {
+        let body = serde_json::to_vec(message).map_err(HttpError::Json)?;
+        let reply = post(&self.url, self.max_body_length, body, expects_reply).await?;
+        reply
+            .map(|reply| serde_json::from_slice(&reply))
+            .transpose()
+            .map_err(HttpError::Json)
+    }
+}
+
+impl Sink<Value> for HttpTransport {
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        if self.closed {
+            return Poll::Ready(Err(TransportError::Closed));
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Value) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if this.closed {
+            return Err(TransportError::Closed);
+        }
+
+        let (url, max_body_length) = (this.url.clone(), this.max_body_length);
+        let ids = request_ids(&item);
+        this.in_flight.push(Box::pin(async move {
+            let reply = async {
+                let body = serde_json::to_vec(&item).map_err(HttpError::Json)?;
+                let reply = post(&url, max_body_length, body, !ids.is_empty()).await?;
+                reply
+                    .map(|reply| serde_json::from_slice(&reply))
+                    .transpose()
+                    .map_err(HttpError::Json)
+            };
+            reply.await.map_err(|error| TransportError::Unanswered {
+                ids,
+                error: error.into(),
+            })
+        }));
+        if let Some(waker) = this.waker.take() {
+            waker.wake();
+        }
+        Ok(())
+    }
+
+    /// Messages are posted as the stream is polled, so there is nothing to
+    /// wait for.
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    /// Ends the stream once the posts in flight are answered.
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.closed = true;
+        if let Some(waker) = this.waker.take() {
+            waker.wake();
+        }
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for HttpTransport {
+    type Item = Result<Value, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            match this.in_flight.poll_next_unpin(cx) {
+                Poll::Ready(Some(Ok(Some(reply)))) => return Poll::Ready(Some(Ok(reply))),
+                Poll::Ready(Some(Ok(None))) => continue,
+                Poll::Ready(Some(Err(error))) => return Poll::Ready(Some(Err(error))),
+                Poll::Ready(None) if this.closed => return Poll::Ready(None),
+                Poll::Ready(None) => {
+                    this.waker = Some(cx.waker().clone());
+                    return Poll::Pending;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+    }
+}
+
+impl From<HttpError> for io::Error {
+    fn from(error: HttpError) -> Self {
+        match error {
+            HttpError::Io(error) => error,
+            error => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
+
+/// The ids of the requests in `message`, which the server has to answer.
+fn request_ids(message: &Value) -> Vec<Id> {
+    let request_id = |message: &Value| {
+        let object = message.as_object()?;
+        if !object.contains_key("method") || is_notification(object) {
+            return None;
+        }
+        serde_json::from_value(object["id"].clone()).ok()
+    };
+    match message {
+        Value::Array(entries) => entries.iter().filter_map(request_id).collect(),
+        message => request_id(message).into_iter().collect(),
+    }
+}
+
+/// Posts `body` to `url`, returning the body of the response if
+/// `expects_reply`.
+async fn post(
+    url: &Url,
+    max_body_length: usize,
+    body: Vec<u8>,
+    expects_reply: bool,
+) -> Result<Option<Vec<u8>>, HttpError> {
+    let head = format!(
+        "POST {} HTTP/1.1\r\nHost: {}\r\nContent-Type: application/json\r\n\
+         Accept: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        url.path,
+        url.host,
+        body.len()
+    );
+
+    let mut io = tcp::connect_io(url.addr.clone()).await?;
+    io.write_all(head.as_bytes()).await?;
+    io.write_all(&body).await?;
+    io.flush().await?;
+
+    let mut buffer = Vec::new();
+    let head = read_head(&mut io, &mut buffer)
+        .await?
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let (status, reason) = status(&head)?;
+    if !(200..300).contains(&status) {
+        return Err(HttpError::Status { status, reason });
+    }
+
+    let body = if status == 204 {
+        Vec::new()
+    } else {
+        read_response_body(&mut io, &mut buffer, &head, max_body_length).await?
+    };
+
+    if !expects_reply {
+        return Ok(None);
+    }
+    if body.iter().all(u8::is_ascii_whitespace) {
+        return Err(HttpError::EmptyBody);
+    }
+    let content_type = head.header("Content-Type");
+    if !content_type.is_some_and(is_json) {
+        return Err(HttpError::ContentType(content_type.map(str::to_string)));
+    }
+    Ok(Some(body))
+}
+
+/// Reads the status code and reason phrase from the status line.
+fn status(head: &Head) -> io::Result<(u16, String)> {
+    let mut parts = head.start_line.splitn(3, ' ');
+    match (parts.next(), parts.next(), parts.next()) {
+        (Some(version), Some(status), reason) if version.starts_with("HTTP/1.") => {
+            let status = status
+                .parse()
+                .map_err(|_| invalid(format!("invalid status {status:?}")))?;
+            Ok((status, reason.unwrap_or_default().to_string()))
+        }
+        _ => Err(invalid(format!(
+            "invalid status line {:?}",
+            head.start_line
+        ))),
+    }
+}
+
+/// Reads a body of at most `max_length` bytes.
+async fn read_response_body<R>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+    head: &Head,
+    max_length: usize,
+) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    if head.has_token("Transfer-Encoding", "chunked") {
+        return read_chunked(reader, buffer, max_length).await;
+    }
+    if let Some(length) = head.content_length()? {
+        if length > max_length {
+            return Err(too_long(max_length));
+        }
+        return read_body(reader, buffer, length).await;
+    }
+
+    // Without a length, the body runs until the server closes the connection.
+    loop {
+        if buffer.len() > max_length {
+            return Err(too_long(max_length));
+        }
+        if read_more(reader, buffer).await? == 0 {
+            return Ok(std::mem::take(buffer));
+        }
+    }
+}
+
+/// Reads a body sent with the `chunked` transfer coding.
+async fn read_chunked<R>(
+    reader: &mut R,
+    buffer: &mut Vec<u8>,
+    max_length: usize,
+) -> io::Result<Vec<u8>>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut body = Vec::new();
+    loop {
+        let line = read_line(reader, buffer).await?;
+        let size = line.split(';').next().unwrap_or_default().trim();
+        let size = usize::from_str_radix(size, 16)
+            .map_err(|_| invalid(format!("invalid chunk size {size:?}")))?;
+
+        if size == 0 {
+            // Skip the trailers, up to the empty line that ends them.
+            while !read_line(reader, buffer).await?.is_empty() {}
+            return Ok(body);
+        }
+
+        if body.len().saturating_add(size) > max_length {
+            return Err(too_long(max_length));
+        }
+        body.extend(read_body(reader, buffer, size).await?);
+        if !read_line(reader, buffer).await?.is_empty() {
+            return Err(invalid("chunk is longer than its size"));
+        }
+    }
+}
+
+fn too_long(max_length: usize) -> io::Error {
+    invalid(format!(
+        "response body exceeds the maximum length of {max_length} bytes"
+    ))
+}
+
+async fn read_line<R>(reader: &mut R, buffer: &mut Vec<u8>) -> io::Result<String>
+where
+    R: AsyncRead + Unpin,
+{
+    loop {
+        if let Some(end) = buffer.windows(2).position(|window| window == b"\r\n") {
+            let line: Vec<u8> = buffer.drain(..end + 2).take(end).collect();
+            return String::from_utf8(line).map_err(|_| invalid("line is not ASCII"));
+        }
+        if read_more(reader, buffer).await? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorObject, Header, Request};
+    use futures::executor::block_on;
+    use serde_json::{json, Value};
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Answers a single request with `response`, returning the URL to post to.
+    fn reply_with(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0; 1024];
+            while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                let read = stream.read(&mut buf).unwrap();
+                request.extend_from_slice(&buf[..read]);
+            }
+            stream.write_all(response.as_bytes()).unwrap();
+        });
+        format!("http://{addr}/rpc")
+    }
+
+    /// Answers calls to `fail` with `failure`, and the others with `19` once
+    /// that is sent, so that they are still in flight when a call fails.
+    #[cfg(feature = "client")]
+    fn fail_first(failure: &'static str) -> String {
+        use std::sync::{mpsc, Arc, Mutex};
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (failed, after_failure) = mpsc::channel();
+        let after_failure = Arc::new(Mutex::new(after_failure));
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let (failed, after_failure) = (failed.clone(), Arc::clone(&after_failure));
+                thread::spawn(move || {
+                    let mut request = Vec::new();
+                    let mut buf = [0; 1024];
+                    let body = loop {
+                        let read = stream.read(&mut buf).unwrap();
+                        request.extend_from_slice(&buf[..read]);
+                        let text = String::from_utf8_lossy(&request);
+                        let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                            continue;
+                        };
+                        let length = head
+                            .lines()
+                            .find_map(|line| line.strip_prefix("Content-Length: "))
+                            .unwrap();
+                        if body.len() == length.parse::<usize>().unwrap() {
+                            break serde_json::from_str::<Value>(body).unwrap();
+                        }
+                    };
+
+                    if body["method"] == "fail" {
+                        stream.write_all(failure.as_bytes()).unwrap();
+                        failed.send(()).unwrap();
+                        return;
+                    }
+                    after_failure.lock().unwrap().recv().unwrap();
+                    let reply =
+                        json!({"jsonrpc": "2.0", "id": body["id"], "result": 19}).to_string();
+                    let response = format!(
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\n\r\n{reply}",
+                        reply.len()
+                    );
+                    stream.write_all(response.as_bytes()).unwrap();
+                });
+            }
+        });
+        format!("http://{addr}/rpc")
+    }
+
+    /// Calls `fail` and `subtract` concurrently through a client, against a
+    /// server answering the first with `failure`.
+    #[cfg(feature = "client")]
+    fn call_alongside_failure(
+        failure: &'static str,
+    ) -> (
+        Result<i64, crate::ClientError>,
+        Result<i64, crate::ClientError>,
+    ) {
+        use crate::Client;
+        use futures::future;
+
+        let transport = HttpTransport::new(&fail_first(failure)).unwrap();
+        let (client, driver) = Client::connect(transport);
+        let calls = async move {
+            future::join(
+                client.request::<_, i64>("fail", ()),
+                client.request::<_, i64>("subtract", [42, 23]),
+            )
+            .await
+        };
+
+        let (driven, results) = block_on(future::join(driver, calls));
+        driven.unwrap();
+        results
+    }
+
+    fn request() -> JsonRpcRequest<Request<Value>> {
+        JsonRpcRequest {
+            header: Header::v2(1),
+            payload: Request::new("subtract", json!([42, 23])),
+        }
+    }
+
+    #[test]
+    fn test_http_transport_url() {
+        let transport = HttpTransport::new("http://localhost:8080/rpc").unwrap();
+        assert_eq!(transport.url.addr, "localhost:8080");
+        assert_eq!(transport.url.path, "/rpc");
+
+        let transport = HttpTransport::new("http://[::1]").unwrap();
+        assert_eq!(transport.url.addr, "[::1]:80");
+        assert_eq!(transport.url.host, "[::1]");
+        assert_eq!(transport.url.path, "/");
+
+        for url in [
+            "https://localhost/",
+            "localhost:80",
+            "http://",
+            "http://user@host/",
+        ] {
+            assert!(matches!(
+                HttpTransport::new(url),
+                Err(HttpError::InvalidUrl(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_http_transport_response() {
+        let url = reply_with(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 36\r\n\r\n\
+             {\"jsonrpc\":\"2.0\",\"id\":1,\"result\":19}",
+        );
+        let transport = HttpTransport::new(&url).unwrap();
+
+        let response: JsonRpcResponse<i64> = block_on(transport.request(&request())).unwrap();
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), 19));
+    }
+
+    #[test]
+    fn test_http_transport_chunked_response() {
+        let url = reply_with(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n\
+             10\r\n{\"jsonrpc\":\"2.0\"\r\n14\r\n,\"id\":1,\"result\":19}\r\n0\r\n\r\n",
+        );
+        let transport = HttpTransport::new(&url).unwrap();
+
+        let response: JsonRpcResponse<i64> = block_on(transport.request(&request())).unwrap();
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), 19));
+    }
+
+    #[test]
+    fn test_http_transport_errors() {
+        let error_for = |response| {
+            let transport = HttpTransport::new(&reply_with(response)).unwrap();
+            block_on(transport.request::<_, Value, ErrorObject>(&request())).unwrap_err()
+        };
+
+        let error = error_for("HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n");
+        assert!(matches!(error, HttpError::Status { status: 500, .. }));
+
+        let error = error_for("HTTP/1.1 204 No Content\r\n\r\n");
+        assert!(matches!(error, HttpError::EmptyBody));
+
+        let error =
+            error_for("HTTP/1.1 200 OK\r\nContent-Type: text/html\r\nContent-Length: 2\r\n\r\nhi");
+        assert!(matches!(error, HttpError::ContentType(Some(_))));
+
+        let error = error_for("HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\n{}");
+        assert!(matches!(error, HttpError::ContentType(None)));
+    }
+
+    #[test]
+    fn test_http_transport_max_body_length() {
+        let error_for = |response| {
+            let transport = HttpTransport::new(&reply_with(response))
+                .unwrap()
+                .with_max_body_length(8);
+            block_on(transport.request::<_, Value, ErrorObject>(&request())).unwrap_err()
+        };
+        let too_long = |error: HttpError| matches!(error, HttpError::Io(error) if error.kind() == io::ErrorKind::InvalidData);
+
+        assert!(too_long(error_for(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 99999999999\r\n\r\n"
+        )));
+        assert!(too_long(error_for(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\n\r\n\
+             6\r\n[1,2,3\r\n6\r\n,4,5,6\r\n0\r\n\r\n"
+        )));
+        assert!(too_long(error_for(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n\
+             [1,2,3,4,5,6]"
+        )));
+    }
+
+    #[test]
+    fn test_http_transport_error_object_is_a_response() {
+        let url = reply_with(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nConnection: close\r\n\r\n\
+             {\"jsonrpc\":\"2.0\",\"id\":1,\"error\":{\"code\":-32601,\"message\":\"Method not found\"}}",
+        );
+        let transport = HttpTransport::new(&url).unwrap();
+
+        let response: JsonRpcResponse<Value> = block_on(transport.request(&request())).unwrap();
+        assert_eq!(response.into_result(), Err(ErrorObject::method_not_found()));
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_http_transport_error_status_fails_only_its_call() {
+        let (failed, succeeded) = call_alongside_failure(
+            "HTTP/1.1 500 Internal Server Error\r\nContent-Length: 0\r\n\r\n",
+        );
+        let Err(crate::ClientError::Transport(error)) = failed else {
+            panic!("expected a transport error, got {failed:?}");
+        };
+        let error = error.get_ref().unwrap().downcast_ref::<HttpError>();
+        assert!(matches!(error, Some(HttpError::Status { status: 500, .. })));
+        assert_eq!(succeeded.unwrap(), 19);
+    }
+
+    #[cfg(feature = "client")]
+    #[test]
+    fn test_http_transport_invalid_json_fails_only_its_call() {
+        let (failed, succeeded) = call_alongside_failure(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 4\r\n\r\noops",
+        );
+        let Err(crate::ClientError::Transport(error)) = failed else {
+            panic!("expected a transport error, got {failed:?}");
+        };
+        let error = error.get_ref().unwrap().downcast_ref::<HttpError>();
+        assert!(matches!(error, Some(HttpError::Json(_))));
+        assert_eq!(succeeded.unwrap(), 19);
+    }
+
+    #[cfg(feature = "http")]
+    #[test]
+    fn test_http_transport_against_server() {
+        use crate::http::HttpServer;
+        use crate::tcp;
+        use crate::{Router, Server};
+
+        let mut router = Router::new();
+        router.register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b));
+        let server = HttpServer::new(Server::new(router));
+        let listener = tcp::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || block_on(server.serve(listener)));
+
+        let transport = HttpTransport::new(&url).unwrap();
+        let response: JsonRpcResponse<i64> = block_on(transport.request(&request())).unwrap();
+        assert_eq!(response.into_result(), Ok(19));
+
+        let notification = JsonRpcNotification::v2(Request::new("subtract", json!([1, 1])));
+        block_on(transport.notify(&notification)).unwrap();
+
+        let batch = Batch::new(vec![
+            Call::Request(request()),
+            Call::Notification(notification.clone()),
+        ])
+        .unwrap();
+        let responses: Vec<JsonRpcResponse<i64>> = block_on(transport.batch(&batch)).unwrap();
+        assert_eq!(responses, vec![JsonRpcResponse::result(Header::v2(1), 19)]);
+
+        let batch = Batch::new(vec![Call::Notification(notification)]).unwrap();
+        let responses: Vec<JsonRpcResponse<i64>> = block_on(transport.batch(&batch)).unwrap();
+        assert!(responses.is_empty());
+    }
+
+    #[cfg(all(feature = "http", feature = "client"))]
+    #[test]
+    fn test_client_over_http_transport() {
+        use crate::http::HttpServer;
+        use crate::tcp;
+        use crate::{Client, ClientError, Router, Server};
+        use futures::future;
+
+        let mut router = Router::new();
+        router.register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b));
+        let server = HttpServer::new(Server::new(router));
+        let listener = tcp::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        thread::spawn(move || block_on(server.serve(listener)));
+
+        let (client, driver) = Client::connect(HttpTransport::new(&url).unwrap());
+        let calls = async move {
+            let (first, second) = future::join(
+                client.request::<_, i64>("subtract", [42, 23]),
+                client.request::<_, i64>("subtract", [23, 42]),
+            )
+            .await;
+            assert_eq!(first.unwrap(), 19);
+            assert_eq!(second.unwrap(), -19);
+            client.notify("subtract", [1, 1]).unwrap();
+
+            let error = client.request::<_, i64>("missing", ()).await.unwrap_err();
+            assert!(matches!(error, ClientError::Rpc(error) if error.code == -32601));
+            client.close();
+        };
+
+        let (driven, ()) = block_on(future::join(driver, calls));
+        driven.unwrap();
+    }
+}
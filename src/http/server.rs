@@ -0,0 +1,344 @@
+use std::io;
+use std::pin::pin;
+
+use futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+
+use super::{is_json, read_body, read_head, Head, DEFAULT_MAX_BODY_LENGTH};
+use crate::tcp::TcpListener;
+use crate::{Handler, Server};
+
+/// Serves JSON-RPC over HTTP/1.1.
+///
+/// Every `POST` carries a single message or a batch, which is answered with
+/// `200 OK` and the reply as its body, or with `204 No Content` when there is
+/// nothing to reply, as for notifications. Requests that aren't a `POST`,
+/// aren't JSON, or have a body that is too large are rejected with the
+/// matching status code before the body is read. Connections are kept alive
+/// unless the client asks otherwise.
+pub struct HttpServer<H> {
+    server: Server<H>,
+    max_body_length: usize,
+}
+
+impl<H> HttpServer<H>
+where
+    H: Handler,
+{
+    pub fn new(server: Server<H>) -> Self {
+        Self {
+            server,
+            max_body_length: DEFAULT_MAX_BODY_LENGTH,
+        }
+    }
+
+    /// Rejects bodies longer than `max_body_length` bytes with
+    /// `413 Payload Too Large`.
+    pub fn with_max_body_length(mut self, max_body_length: usize) -> Self {
+        self.max_body_length = max_body_length;
+        self
+    }
+
+    pub fn max_body_length(&self) -> usize {
+        self.max_body_length
+    }
+
+    /// Serves every connection accepted by `listener` concurrently. Failing
+    /// to accept a connection doesn't stop the others from being served.
+    ///
+    /// Calls carry the [`SocketAddr`](std::net::SocketAddr) of the peer in
+    /// their [`Context`](crate::Context) extensions.
+    pub async fn serve(&self, listener: TcpListener) {
+        let mut connections = pin!(listener.incoming_io().fuse());
+        let mut sessions = FuturesUnordered::new();
+
+        loop {
+            futures::select! {
+                connection = connections.next() => match connection {
+                    Some(Ok(connection)) => {
+                        let session = Self {
+                            server: self.server.with_extensions(connection.extensions),
+                            max_body_length: self.max_body_length,
+                        };
+                        sessions.push(async move {
+                            session.serve_connection(connection.transport).await
+                        });
+                    }
+                    Some(Err(_)) => {}
+                    None => break,
+                },
+                _ = sessions.select_next_some() => {}
+            }
+        }
+
+        while sessions.next().await.is_some() {}
+    }
+
+    /// Serves the requests received on `io` until either side closes the
+    /// connection.
+    pub async fn serve_connection<T>(&self, mut io: T) -> io::Result<()>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut buffer = Vec::new();
+        loop {
+            let response = match read_head(&mut io, &mut buffer).await {
+                Ok(Some(head)) => self.respond(head, &mut io, &mut buffer).await?,
+                Ok(None) => break,
+                Err(error) if error.kind() == io::ErrorKind::InvalidData => {
+                    Response::status(400, "Bad Request")
+                }
+                Err(error) => return Err(error),
+            };
+
+            io.write_all(&response.encode()).await?;
+            io.flush().await?;
+            if response.close {
+                break;
+            }
+        }
+
+        io.close().await
+    }
+
+    async fn respond<T>(&self, head: Head, io: &mut T, buffer: &mut Vec<u8>) -> io::Result<Response>
+    where
+        T: AsyncRead + AsyncWrite + Unpin,
+    {
+        let mut parts = head.start_line.split(' ');
+        let (Some(method), Some(_), Some(version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Ok(Response::status(400, "Bad Request"));
+        };
+
+        let keep_alive = match version {
+            "HTTP/1.1" => !head.has_token("Connection", "close"),
+            "HTTP/1.0" => head.has_token("Connection", "keep-alive"),
+            _ => return Ok(Response::status(505, "HTTP Version Not Supported")),
+        };
+
+        if method != "POST" {
+            let mut response = Response::status(405, "Method Not Allowed");
+            response.headers.push(("Allow", "POST"));
+            return Ok(response);
+        }
+
+        if head.header("Transfer-Encoding").is_some() {
+            return Ok(Response::status(411, "Length Required"));
+        }
+        let length = match head.content_length() {
+            Ok(Some(length)) => length,
+            Ok(None) => return Ok(Response::status(411, "Length Required")),
+            Err(_) => return Ok(Response::status(400, "Bad Request")),
+        };
+        if length > self.max_body_length {
+            return Ok(Response::status(413, "Payload Too Large"));
+        }
+        if !head.header("Content-Type").is_some_and(is_json) {
+            return Ok(Response::status(415, "Unsupported Media Type"));
+        }
+
+        if head.has_token("Expect", "100-continue") {
+            io.write_all(b"HTTP/1.1 100 Continue\r\n\r\n").await?;
+            io.flush().await?;
+        }
+        let body = read_body(io, buffer, length).await?;
+
+        let mut response = match self.server.handle_bytes(&body).await {
+            Some(reply) => Response {
+                headers: vec![("Content-Type", "application/json")],
+                body: reply,
+                ..Response::status(200, "OK")
+            },
+            None => Response::status(204, "No Content"),
+        };
+        response.close = !keep_alive;
+        Ok(response)
+    }
+}
+
+struct Response {
+    status: u16,
+    reason: &'static str,
+    headers: Vec<(&'static str, &'static str)>,
+    body: Vec<u8>,
+    close: bool,
+}
+
+impl Response {
+    /// A response without a body. The connection is closed after it, as it
+    /// may hold the unread body of the request.
+    fn status(status: u16, reason: &'static str) -> Self {
+        Self {
+            status,
+            reason,
+            headers: Vec::new(),
+            body: Vec::new(),
+            close: true,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        let mut head = format!("HTTP/1.1 {} {}\r\n", self.status, self.reason);
+        for (name, value) in &self.headers {
+            head.push_str(&format!("{name}: {value}\r\n"));
+        }
+        if self.status != 204 {
+            head.push_str(&format!("Content-Length: {}\r\n", self.body.len()));
+        }
+        if self.close {
+            head.push_str("Connection: close\r\n");
+        }
+        head.push_str("\r\n");
+
+        let mut bytes = head.into_bytes();
+        bytes.extend_from_slice(&self.body);
+        bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorObject, Router};
+    use futures::executor::block_on;
+    use serde_json::{json, Value};
+    use std::io::{Read, Write};
+    use std::net::{SocketAddr, TcpStream};
+    use std::thread;
+
+    fn serve(max_body_length: usize) -> SocketAddr {
+        let mut router = Router::new();
+        router
+            .register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b))
+            .register("notify", |_: ()| Ok::<_, ErrorObject>(()));
+        let server = HttpServer::new(Server::new(router)).with_max_body_length(max_body_length);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || block_on(server.serve(listener)));
+        addr
+    }
+
+    fn post(addr: SocketAddr, content_type: &str, body: &str) -> (String, Option<Value>) {
+        let request = format!(
+            "POST /rpc HTTP/1.1\r\nHost: localhost\r\nContent-Type: {content_type}\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        );
+        send(addr, &request)
+    }
+
+    /// Sends a raw request, returning the status line and the body, if any.
+    fn send(addr: SocketAddr, request: &str) -> (String, Option<Value>) {
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.lines().next().unwrap().to_string();
+        if head.contains("Content-Type: application/json") {
+            (status, Some(serde_json::from_str(body).unwrap()))
+        } else {
+            assert!(body.is_empty());
+            (status, None)
+        }
+    }
+
+    #[test]
+    fn test_http_single_request() {
+        let addr = serve(DEFAULT_MAX_BODY_LENGTH);
+        let (status, body) = post(
+            addr,
+            "application/json",
+            r#"{"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}"#,
+        );
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body, Some(json!({"jsonrpc": "2.0", "result": 19, "id": 1})));
+    }
+
+    #[test]
+    fn test_http_batch() {
+        let addr = serve(DEFAULT_MAX_BODY_LENGTH);
+        let (status, body) = post(
+            addr,
+            "application/json; charset=utf-8",
+            r#"[
+                {"jsonrpc": "2.0", "method": "subtract", "params": [2, 1], "id": 1},
+                {"jsonrpc": "2.0", "method": "notify"}
+            ]"#,
+        );
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(
+            body,
+            Some(json!([{"jsonrpc": "2.0", "result": 1, "id": 1}]))
+        );
+    }
+
+    #[test]
+    fn test_http_notifications_only() {
+        let addr = serve(DEFAULT_MAX_BODY_LENGTH);
+        let (status, body) = post(
+            addr,
+            "application/json",
+            r#"[{"jsonrpc": "2.0", "method": "notify"}, {"jsonrpc": "2.0", "method": "notify"}]"#,
+        );
+        assert_eq!(status, "HTTP/1.1 204 No Content");
+        assert_eq!(body, None);
+    }
+
+    #[test]
+    fn test_http_parse_error() {
+        let addr = serve(DEFAULT_MAX_BODY_LENGTH);
+        let (status, body) = post(addr, "application/json", "{");
+        assert_eq!(status, "HTTP/1.1 200 OK");
+        assert_eq!(body.unwrap()["error"]["code"], json!(-32700));
+    }
+
+    #[test]
+    fn test_http_rejections() {
+        let addr = serve(16);
+
+        let (status, _) = post(addr, "text/plain", "{}");
+        assert_eq!(status, "HTTP/1.1 415 Unsupported Media Type");
+
+        let (status, _) = post(addr, "application/json", &" ".repeat(17));
+        assert_eq!(status, "HTTP/1.1 413 Payload Too Large");
+
+        let (status, _) = send(addr, "GET / HTTP/1.1\r\nHost: localhost\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 405 Method Not Allowed");
+
+        let (status, _) = send(
+            addr,
+            "POST / HTTP/1.1\r\nContent-Type: application/json\r\n\r\n",
+        );
+        assert_eq!(status, "HTTP/1.1 411 Length Required");
+
+        let (status, _) = send(addr, "POST /\r\n\r\n");
+        assert_eq!(status, "HTTP/1.1 400 Bad Request");
+    }
+
+    #[test]
+    fn test_http_keep_alive() {
+        let addr = serve(DEFAULT_MAX_BODY_LENGTH);
+        let body = r#"{"jsonrpc": "2.0", "method": "subtract", "params": [2, 1], "id": 1}"#;
+        let request = format!(
+            "POST / HTTP/1.1\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+
+        let mut stream = TcpStream::connect(addr).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        stream.write_all(request.as_bytes()).unwrap();
+        stream.shutdown(std::net::Shutdown::Write).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+        assert_eq!(response.matches("HTTP/1.1 200 OK").count(), 2);
+        assert!(!response.contains("Connection: close"));
+    }
+}
@@ -0,0 +1,143 @@
+use std::fmt;
+
+use serde_json::Number;
+
+/// A JSON-RPC request identifier.
+///
+/// The specification allows an id to be a string, a number or `null`. The
+/// value is kept exactly as received so that a response echoes `"1"` for a
+/// string id and `1` for a numeric one.
+#[derive(Clone, Debug, Eq, Hash, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(untagged)]
+pub enum Id {
+    Number(Number),
+    String(String),
+    Null,
+}
+
+impl Id {
+    pub fn is_null(&self) -> bool {
+        matches!(self, Self::Null)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Self::String(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn as_number(&self) -> Option<&Number> {
+        match self {
+            Self::Number(id) => Some(id),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Id {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Number(id) => write!(f, "{id}"),
+            // Quoted and escaped as on the wire.
+            Self::String(id) => f.write_str(&serde_json::to_string(id).map_err(|_| fmt::Error)?),
+            Self::Null => f.write_str("null"),
+        }
+    }
+}
+
+macro_rules! impl_from_integer {
+    ($($ty:ty),*) => {
+        $(
+            impl From<$ty> for Id {
+                fn from(id: $ty) -> Self {
+                    Self::Number(id.into())
+                }
+            }
+        )*
+    };
+}
+
+impl_from_integer!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize);
+
+impl From<Number> for Id {
+    fn from(id: Number) -> Self {
+        Self::Number(id)
+    }
+}
+
+impl From<String> for Id {
+    fn from(id: String) -> Self {
+        Self::String(id)
+    }
+}
+
+impl From<&str> for Id {
+    fn from(id: &str) -> Self {
+        Self::String(id.to_string())
+    }
+}
+
+impl<T> From<Option<T>> for Id
+where
+    T: Into<Id>,
+{
+    fn from(id: Option<T>) -> Self {
+        id.map_or(Self::Null, Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_id_serialization() {
+        assert_eq!(serde_json::to_value(Id::from(1)).unwrap(), json!(1));
+        assert_eq!(serde_json::to_value(Id::from(-7)).unwrap(), json!(-7));
+        assert_eq!(serde_json::to_value(Id::from("1")).unwrap(), json!("1"));
+        assert_eq!(serde_json::to_value(Id::Null).unwrap(), json!(null));
+    }
+
+    #[test]
+    fn test_id_deserialization() {
+        let id: Id = serde_json::from_str("42").unwrap();
+        assert_eq!(id, Id::from(42));
+
+        let id: Id = serde_json::from_str("-42").unwrap();
+        assert_eq!(id, Id::from(-42));
+
+        let id: Id = serde_json::from_str("\"abc\"").unwrap();
+        assert_eq!(id, Id::from("abc"));
+
+        let id: Id = serde_json::from_str("null").unwrap();
+        assert_eq!(id, Id::Null);
+
+        assert!(serde_json::from_str::<Id>("true").is_err());
+        assert!(serde_json::from_str::<Id>("[1]").is_err());
+        assert!(serde_json::from_str::<Id>("{}").is_err());
+    }
+
+    #[test]
+    fn test_id_round_trip() {
+        for raw in ["1", "\"1\"", "-3", "1.5", "null", "\"\""] {
+            let id: Id = serde_json::from_str(raw).unwrap();
+            assert_eq!(serde_json::to_string(&id).unwrap(), raw);
+        }
+    }
+
+    #[test]
+    fn test_id_from_option() {
+        assert_eq!(Id::from(Some(3u64)), Id::from(3u64));
+        assert_eq!(Id::from(None::<u64>), Id::Null);
+    }
+
+    #[test]
+    fn test_id_display() {
+        assert_eq!(Id::from(7).to_string(), "7");
+        assert_eq!(Id::from("7").to_string(), "\"7\"");
+        assert_eq!(Id::from("a\u{1}é").to_string(), "\"a\\u0001é\"");
+        assert_eq!(Id::Null.to_string(), "null");
+    }
+}
@@ -1,3 +1,77 @@
+use std::borrow::Cow;
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Error as _, IgnoredAny, MapAccess, Visitor};
+use serde::ser::SerializeMap;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+mod batch;
+#[cfg(any(feature = "tcp", all(unix, feature = "unix"), feature = "process"))]
+mod blocking;
+#[cfg(any(feature = "client", feature = "server"))]
+mod cancellation;
+#[cfg(feature = "client")]
+mod client;
+#[cfg(feature = "codec")]
+pub mod codec;
+mod error;
+mod extensions;
+#[cfg(any(feature = "http", feature = "http-client", feature = "websocket"))]
+pub mod http;
+mod id;
+mod message;
+mod method;
+mod notification;
+#[cfg(all(feature = "client", feature = "server"))]
+mod peer;
+#[cfg(feature = "process")]
+pub mod process;
+#[cfg(feature = "server")]
+mod progress;
+mod request;
+#[cfg(feature = "server")]
+mod router;
+#[cfg(feature = "server")]
+mod server;
+#[cfg(feature = "tcp")]
+pub mod tcp;
+#[cfg(any(feature = "client", feature = "server", feature = "codec"))]
+pub mod transport;
+#[cfg(all(unix, feature = "unix"))]
+pub mod unix;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+pub use batch::{Batch, BatchEntry, Call, InvalidEntry};
+#[cfg(any(feature = "tcp", all(unix, feature = "unix"), feature = "process"))]
+pub use blocking::BlockingIo;
+#[cfg(any(feature = "tcp", all(unix, feature = "unix")))]
+pub use blocking::Incoming;
+#[cfg(any(feature = "client", feature = "server"))]
+pub use cancellation::Cancellation;
+#[cfg(feature = "client")]
+pub use client::{Client, ClientError};
+pub use error::{error_code, ErrorObject};
+pub use extensions::Extensions;
+pub use id::Id;
+pub use message::Message;
+pub use method::{Method, MethodRequest, MethodResponse};
+pub use notification::JsonRpcNotification;
+#[cfg(all(feature = "client", feature = "server"))]
+pub use peer::Peer;
+#[cfg(feature = "server")]
+pub use progress::{Progress, ProgressParams, ProgressValue};
+pub use request::{Params, Request};
+#[cfg(feature = "server")]
+pub use router::Router;
+#[cfg(feature = "server")]
+pub use server::{Context, Handler, Reply, Server};
+
+/// The protocol version of a message.
+///
+/// JSON-RPC 1.0 has no `jsonrpc` member: it is omitted when serializing
+/// [`Version::One`], and a message without it is read as 1.0.
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum Version {
     #[serde(rename = "1.0")]
@@ -6,29 +80,40 @@ pub enum Version {
     Two,
 }
 
+impl Version {
+    fn one() -> Self {
+        Self::One
+    }
+
+    fn is_one(&self) -> bool {
+        matches!(self, Self::One)
+    }
+}
+
 #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct Header {
+    #[serde(default = "Version::one", skip_serializing_if = "Version::is_one")]
     pub jsonrpc: Version,
-    pub id: Option<usize>,
+    pub id: Id,
 }
 
 impl Header {
-    pub fn v1(id: Option<usize>) -> Self {
+    pub fn v1(id: impl Into<Id>) -> Self {
         Self {
             jsonrpc: Version::One,
-            id,
+            id: id.into(),
         }
     }
 
-    pub fn v2(id: Option<usize>) -> Self {
+    pub fn v2(id: impl Into<Id>) -> Self {
         Self {
             jsonrpc: Version::Two,
-            id,
+            id: id.into(),
         }
     }
 }
 
-#[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
 pub struct JsonRpcRequest<T> {
     #[serde(flatten)]
     pub header: Header,
@@ -36,8 +121,12 @@ pub struct JsonRpcRequest<T> {
     pub payload: T,
 }
 
-#[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-pub struct JsonRpcResponse<R, E>(pub JsonRpcRequest<Response<R, E>>);
+/// A JSON-RPC response.
+///
+/// In 2.0 exactly one of `result` or `error` is present. In 1.0 both members
+/// are always present, with the unused one set to `null`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonRpcResponse<R, E = ErrorObject>(pub JsonRpcRequest<Response<R, E>>);
 
 impl<R, E> JsonRpcResponse<R, E> {
     pub fn result(header: Header, result: R) -> Self {
@@ -53,32 +142,296 @@ impl<R, E> JsonRpcResponse<R, E> {
             payload: Response::error(error),
         })
     }
+
+    pub fn into_result(self) -> Result<R, E> {
+        self.0.payload.into_result()
+    }
+}
+
+impl<R, E> Serialize for JsonRpcResponse<R, E>
+where
+    R: Serialize,
+    E: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let JsonRpcRequest { header, payload } = &self.0;
+        if header.jsonrpc == Version::Two {
+            return self.0.serialize(serializer);
+        }
+
+        let mut map = serializer.serialize_map(Some(3))?;
+        match payload {
+            Response::Result(result) => {
+                map.serialize_entry("result", result)?;
+                map.serialize_entry("error", &())?;
+            }
+            Response::Error(error) => {
+                map.serialize_entry("result", &())?;
+                map.serialize_entry("error", error)?;
+            }
+        }
+        map.serialize_entry("id", &header.id)?;
+        map.end()
+    }
+}
+
+/// A response member that keeps `null` apart from a present value, since a
+/// 1.0 response sets the member it doesn't use to `null`.
+enum Member<T> {
+    Null,
+    Value(T),
+}
+
+impl<T> Member<T> {
+    /// Produces the value, deserializing `T` from `null` when needed.
+    fn into_value<'de, Err>(self) -> Result<T, Err>
+    where
+        T: Deserialize<'de>,
+        Err: serde::de::Error,
+    {
+        match self {
+            Self::Null => T::deserialize(serde::de::value::UnitDeserializer::new()),
+            Self::Value(value) => Ok(value),
+        }
+    }
 }
 
-#[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-pub struct Response<R, E> {
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub result: Option<R>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub error: Option<E>,
+impl<'de, T> Deserialize<'de> for Member<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct MemberVisitor<T>(PhantomData<T>);
+
+        impl<'de, T> Visitor<'de> for MemberVisitor<T>
+        where
+            T: Deserialize<'de>,
+        {
+            type Value = Member<T>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a response member")
+            }
+
+            fn visit_none<Err>(self) -> Result<Self::Value, Err> {
+                Ok(Member::Null)
+            }
+
+            fn visit_unit<Err>(self) -> Result<Self::Value, Err> {
+                Ok(Member::Null)
+            }
+
+            fn visit_some<D>(self, deserializer: D) -> Result<Self::Value, D::Error>
+            where
+                D: Deserializer<'de>,
+            {
+                T::deserialize(deserializer).map(Member::Value)
+            }
+        }
+
+        deserializer.deserialize_option(MemberVisitor(PhantomData))
+    }
+}
+
+impl<'de, R, E> Deserialize<'de> for JsonRpcResponse<R, E>
+where
+    R: Deserialize<'de>,
+    E: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct JsonRpcResponseVisitor<R, E>(PhantomData<(R, E)>);
+
+        impl<'de, R, E> Visitor<'de> for JsonRpcResponseVisitor<R, E>
+        where
+            R: Deserialize<'de>,
+            E: Deserialize<'de>,
+        {
+            type Value = JsonRpcResponse<R, E>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON-RPC response object")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut jsonrpc = None;
+                let mut id = None;
+                let mut result = None;
+                let mut error = None;
+
+                while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+                    match &*key {
+                        "jsonrpc" if jsonrpc.is_none() => jsonrpc = Some(map.next_value()?),
+                        "id" if id.is_none() => id = Some(map.next_value()?),
+                        "result" if result.is_none() => {
+                            result = Some(map.next_value::<Member<R>>()?)
+                        }
+                        "error" if error.is_none() => error = Some(map.next_value::<Member<E>>()?),
+                        "jsonrpc" | "id" | "result" | "error" => {
+                            return Err(A::Error::custom(format_args!("duplicate field `{key}`")))
+                        }
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                        }
+                    }
+                }
+
+                let jsonrpc = jsonrpc.unwrap_or(Version::One);
+                let id = id.ok_or_else(|| A::Error::missing_field("id"))?;
+                let payload = match (&jsonrpc, result, error) {
+                    (Version::Two, Some(result), None) => Response::Result(result.into_value()?),
+                    (Version::Two, None, Some(error)) => Response::Error(error.into_value()?),
+                    (Version::Two, Some(_), Some(_)) => {
+                        return Err(A::Error::custom(
+                            "response must not contain both `result` and `error`",
+                        ))
+                    }
+                    (Version::One, None | Some(Member::Null), Some(Member::Value(error))) => {
+                        Response::Error(error)
+                    }
+                    (Version::One, Some(Member::Value(_)), Some(Member::Value(_))) => {
+                        return Err(A::Error::custom(
+                            "response must not contain both a non-null `result` and `error`",
+                        ))
+                    }
+                    (Version::One, Some(result), None | Some(Member::Null)) => {
+                        Response::Result(result.into_value()?)
+                    }
+                    (_, None, None) | (Version::One, None, Some(Member::Null)) => {
+                        return Err(A::Error::custom(
+                            "response must contain either `result` or `error`",
+                        ))
+                    }
+                };
+
+                Ok(JsonRpcResponse(JsonRpcRequest {
+                    header: Header { jsonrpc, id },
+                    payload,
+                }))
+            }
+        }
+
+        deserializer.deserialize_map(JsonRpcResponseVisitor(PhantomData))
+    }
+}
+
+/// The outcome carried by a JSON-RPC response: exactly one of `result` or
+/// `error`.
+///
+/// Deserialization rejects objects that carry both members, or neither.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Response<R, E> {
+    Result(R),
+    Error(E),
 }
 
 impl<R, E> Response<R, E> {
     pub fn result(result: R) -> Self {
-        Self {
-            result: Some(result),
-            error: None,
-        }
+        Self::Result(result)
     }
 
     pub fn error(error: E) -> Self {
-        Self {
-            result: None,
-            error: Some(error),
+        Self::Error(error)
+    }
+
+    pub fn is_result(&self) -> bool {
+        matches!(self, Self::Result(_))
+    }
+
+    pub fn is_error(&self) -> bool {
+        matches!(self, Self::Error(_))
+    }
+
+    pub fn as_result(&self) -> Result<&R, &E> {
+        match self {
+            Self::Result(result) => Ok(result),
+            Self::Error(error) => Err(error),
+        }
+    }
+
+    pub fn into_result(self) -> Result<R, E> {
+        match self {
+            Self::Result(result) => Ok(result),
+            Self::Error(error) => Err(error),
         }
     }
 }
 
+impl<R, E> From<Result<R, E>> for Response<R, E> {
+    fn from(result: Result<R, E>) -> Self {
+        match result {
+            Ok(result) => Self::Result(result),
+            Err(error) => Self::Error(error),
+        }
+    }
+}
+
+impl<'de, R, E> Deserialize<'de> for Response<R, E>
+where
+    R: Deserialize<'de>,
+    E: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ResponseVisitor<R, E>(PhantomData<(R, E)>);
+
+        impl<'de, R, E> Visitor<'de> for ResponseVisitor<R, E>
+        where
+            R: Deserialize<'de>,
+            E: Deserialize<'de>,
+        {
+            type Value = Response<R, E>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON-RPC response with either `result` or `error`")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut response = None;
+                while let Some(key) = map.next_key::<Cow<'de, str>>()? {
+                    let member = match &*key {
+                        "result" => Response::Result(map.next_value()?),
+                        "error" => Response::Error(map.next_value()?),
+                        _ => {
+                            map.next_value::<IgnoredAny>()?;
+                            continue;
+                        }
+                    };
+
+                    if response.replace(member).is_some() {
+                        return Err(A::Error::custom(
+                            "response must not contain both `result` and `error`",
+                        ));
+                    }
+                }
+
+                response.ok_or_else(|| {
+                    A::Error::custom("response must contain either `result` or `error`")
+                })
+            }
+        }
+
+        deserializer.deserialize_map(ResponseVisitor(PhantomData))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -106,43 +459,63 @@ mod tests {
 
     #[test]
     fn test_header_constructors() {
-        let header = Header::v1(Some(123));
+        let header = Header::v1(123);
         assert_eq!(header.jsonrpc, Version::One);
-        assert_eq!(header.id, Some(123));
+        assert_eq!(header.id, Id::from(123));
 
-        let header = Header::v1(None);
+        let header = Header::v1(Id::Null);
         assert_eq!(header.jsonrpc, Version::One);
-        assert_eq!(header.id, None);
+        assert_eq!(header.id, Id::Null);
 
-        let header = Header::v2(Some(456));
+        let header = Header::v2(456);
         assert_eq!(header.jsonrpc, Version::Two);
-        assert_eq!(header.id, Some(456));
+        assert_eq!(header.id, Id::from(456));
 
-        let header = Header::v2(None);
+        let header = Header::v2("abc");
         assert_eq!(header.jsonrpc, Version::Two);
-        assert_eq!(header.id, None);
+        assert_eq!(header.id, Id::from("abc"));
+
+        let header = Header::v2(Id::Null);
+        assert_eq!(header.jsonrpc, Version::Two);
+        assert_eq!(header.id, Id::Null);
     }
 
     #[test]
     fn test_header_serialization() {
-        let header = Header::v2(Some(42));
+        let header = Header::v2(42);
         let json = serde_json::to_value(&header).unwrap();
         assert_eq!(json, json!({"jsonrpc": "2.0", "id": 42}));
 
-        let header = Header::v1(None);
+        let header = Header::v2("42");
+        let json = serde_json::to_value(&header).unwrap();
+        assert_eq!(json, json!({"jsonrpc": "2.0", "id": "42"}));
+
+        let header = Header::v1(Id::Null);
         let json = serde_json::to_value(&header).unwrap();
-        assert_eq!(json, json!({"jsonrpc": "1.0", "id": null}));
+        assert_eq!(json, json!({"id": null}));
     }
 
     #[test]
     fn test_header_deserialization() {
         let json = r#"{"jsonrpc": "1.0", "id": 123}"#;
         let header: Header = serde_json::from_str(json).unwrap();
-        assert_eq!(header, Header::v1(Some(123)));
+        assert_eq!(header, Header::v1(123));
 
         let json = r#"{"jsonrpc": "2.0", "id": null}"#;
         let header: Header = serde_json::from_str(json).unwrap();
-        assert_eq!(header, Header::v2(None));
+        assert_eq!(header, Header::v2(Id::Null));
+
+        let json = r#"{"id": 7}"#;
+        let header: Header = serde_json::from_str(json).unwrap();
+        assert_eq!(header, Header::v1(7));
+
+        let json = r#"{"jsonrpc": "2.0", "id": "req-1"}"#;
+        let header: Header = serde_json::from_str(json).unwrap();
+        assert_eq!(header, Header::v2("req-1"));
+
+        let json = r#"{"jsonrpc": "2.0", "id": -1.5}"#;
+        let header: Header = serde_json::from_str(json).unwrap();
+        assert_eq!(serde_json::to_string(&header.id).unwrap(), "-1.5");
     }
 
     #[test]
@@ -153,7 +526,7 @@ mod tests {
             params: Vec<String>,
         }
 
-        let header = Header::v2(Some(1));
+        let header = Header::v2(1);
         let payload = TestPayload {
             method: "test".to_string(),
             params: vec!["a".to_string(), "b".to_string()],
@@ -178,15 +551,14 @@ mod tests {
 
     #[test]
     fn test_json_rpc_response_result() {
-        let header = Header::v2(Some(42));
+        let header = Header::v2(42);
         let result = "success".to_string();
 
         let response: JsonRpcResponse<String, ()> =
             JsonRpcResponse::result(header.clone(), result.clone());
 
         assert_eq!(response.0.header, header);
-        assert_eq!(response.0.payload.result, Some(result.clone()));
-        assert_eq!(response.0.payload.error, None);
+        assert_eq!(response.0.payload, Response::Result(result.clone()));
 
         let json = serde_json::to_value(&response).unwrap();
         assert_eq!(
@@ -200,8 +572,7 @@ mod tests {
 
         let deserialized: JsonRpcResponse<String, Value> = serde_json::from_value(json).unwrap();
         assert_eq!(deserialized.0.header, header);
-        assert_eq!(deserialized.0.payload.result, Some(result));
-        assert_eq!(deserialized.0.payload.error, None);
+        assert_eq!(deserialized.into_result(), Ok(result));
     }
 
     #[test]
@@ -212,7 +583,7 @@ mod tests {
             message: String,
         }
 
-        let header = Header::v2(Some(42));
+        let header = Header::v2(42);
         let error = TestError {
             code: -32600,
             message: "Invalid Request".to_string(),
@@ -222,8 +593,7 @@ mod tests {
             JsonRpcResponse::error(header.clone(), error.clone());
 
         assert_eq!(response.0.header, header);
-        assert_eq!(response.0.payload.error, Some(error.clone()));
-        assert_eq!(response.0.payload.result, None);
+        assert_eq!(response.0.payload, Response::Error(error.clone()));
 
         let json = serde_json::to_value(&response).unwrap();
         assert_eq!(
@@ -240,19 +610,162 @@ mod tests {
 
         let deserialized: JsonRpcResponse<Value, TestError> = serde_json::from_value(json).unwrap();
         assert_eq!(deserialized.0.header, header);
-        assert_eq!(deserialized.0.payload.error, Some(error));
-        assert_eq!(deserialized.0.payload.result, None);
+        assert_eq!(deserialized.into_result(), Err(error));
+    }
+
+    #[test]
+    fn test_json_rpc_response_error_object() {
+        let header = Header::v2("abc");
+        let response: JsonRpcResponse<()> =
+            JsonRpcResponse::error(header.clone(), ErrorObject::method_not_found());
+
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "jsonrpc": "2.0",
+                "id": "abc",
+                "error": {
+                    "code": -32601,
+                    "message": "Method not found"
+                }
+            })
+        );
+
+        let deserialized: JsonRpcResponse<Value> = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized.0.header, header);
+        assert_eq!(
+            deserialized.0.payload,
+            Response::Error(ErrorObject::method_not_found())
+        );
+    }
+
+    #[test]
+    fn test_json_rpc_response_echoes_string_id() {
+        let json = json!({"jsonrpc": "2.0", "id": "1", "method": "ping"});
+        let request: JsonRpcRequest<Value> = serde_json::from_value(json).unwrap();
+
+        let response: JsonRpcResponse<&str, ()> = JsonRpcResponse::result(request.header, "pong");
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, json!({"jsonrpc": "2.0", "id": "1", "result": "pong"}));
+    }
+
+    #[test]
+    fn test_v1_request() {
+        let json = json!({"method": "echo", "params": ["hi"], "id": 1});
+        let request: JsonRpcRequest<Value> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(request.header, Header::v1(1));
+        assert_eq!(serde_json::to_value(&request).unwrap(), json);
+    }
+
+    #[test]
+    fn test_v1_response_serialization() {
+        let response: JsonRpcResponse<&str, Value> = JsonRpcResponse::result(Header::v1(1), "hi");
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, json!({"result": "hi", "error": null, "id": 1}));
+
+        let response: JsonRpcResponse<(), Value> =
+            JsonRpcResponse::error(Header::v1(2), json!("boom"));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(json, json!({"result": null, "error": "boom", "id": 2}));
+    }
+
+    #[test]
+    fn test_v1_response_deserialization() {
+        let json = json!({"result": "hi", "error": null, "id": 1});
+        let response: JsonRpcResponse<String, Value> = serde_json::from_value(json).unwrap();
+        assert_eq!(response.0.header, Header::v1(1));
+        assert_eq!(response.into_result(), Ok("hi".to_string()));
+
+        let json = json!({"result": null, "error": {"code": 1, "message": "boom"}, "id": 1});
+        let response: JsonRpcResponse<String> = serde_json::from_value(json).unwrap();
+        assert_eq!(response.into_result(), Err(ErrorObject::new(1, "boom")));
+
+        let json = json!({"result": null, "error": null, "id": 1});
+        let response: JsonRpcResponse<Option<u32>, Value> = serde_json::from_value(json).unwrap();
+        assert_eq!(response.into_result(), Ok(None));
+
+        let json = json!({"result": 1, "error": "boom", "id": 1});
+        assert!(serde_json::from_value::<JsonRpcResponse<u32, Value>>(json).is_err());
+
+        let json = json!({"error": null, "id": 1});
+        assert!(serde_json::from_value::<JsonRpcResponse<u32, Value>>(json).is_err());
+    }
+
+    #[test]
+    fn test_v1_response_round_trip() {
+        for json in [
+            json!({"result": [1, 2], "error": null, "id": "a"}),
+            json!({"result": null, "error": {"code": 3, "message": "no"}, "id": 9}),
+        ] {
+            let response: JsonRpcResponse<Value> = serde_json::from_value(json.clone()).unwrap();
+            assert_eq!(serde_json::to_value(&response).unwrap(), json);
+        }
+    }
+
+    #[test]
+    fn test_v2_response_rejects_null_member() {
+        let json = json!({"jsonrpc": "2.0", "id": 1, "result": null});
+        let response: JsonRpcResponse<Option<u32>> = serde_json::from_value(json).unwrap();
+        assert_eq!(response.into_result(), Ok(None));
+
+        let json = json!({"jsonrpc": "2.0", "id": 1, "result": null, "error": null});
+        assert!(serde_json::from_value::<JsonRpcResponse<Option<u32>>>(json).is_err());
     }
 
     #[test]
     fn test_response_constructors() {
         let response: Response<&str, ()> = Response::result("success");
-        assert_eq!(response.result, Some("success"));
-        assert_eq!(response.error, None);
+        assert!(response.is_result());
+        assert_eq!(response.as_result(), Ok(&"success"));
+        assert_eq!(response.into_result(), Ok("success"));
 
         let response: Response<(), &str> = Response::error("error");
-        assert_eq!(response.result, None);
-        assert_eq!(response.error, Some("error"));
+        assert!(response.is_error());
+        assert_eq!(response.as_result(), Err(&"error"));
+        assert_eq!(response.into_result(), Err("error"));
+
+        let response: Response<u32, &str> = Ok(1).into();
+        assert_eq!(response, Response::Result(1));
+    }
+
+    #[test]
+    fn test_response_deserialization() {
+        let response: Response<u32, Value> = serde_json::from_value(json!({"result": 1})).unwrap();
+        assert_eq!(response, Response::Result(1));
+
+        let response: Response<Option<u32>, Value> =
+            serde_json::from_value(json!({"result": null})).unwrap();
+        assert_eq!(response, Response::Result(None));
+
+        let response: Response<(), ErrorObject> =
+            serde_json::from_value(json!({"error": {"code": -32603, "message": "Internal error"}}))
+                .unwrap();
+        assert_eq!(response, Response::Error(ErrorObject::internal_error()));
+    }
+
+    #[test]
+    fn test_response_requires_exactly_one_member() {
+        let error = serde_json::from_value::<Response<u32, Value>>(json!({})).unwrap_err();
+        assert!(error.to_string().contains("either `result` or `error`"));
+
+        let json = json!({"result": 1, "error": {"code": -32603, "message": "Internal error"}});
+        let error = serde_json::from_value::<Response<u32, Value>>(json).unwrap_err();
+        assert!(error.to_string().contains("both `result` and `error`"));
+
+        let json = json!({"jsonrpc": "2.0", "id": 1});
+        assert!(serde_json::from_value::<JsonRpcResponse<u32>>(json).is_err());
+
+        let json = json!({
+            "jsonrpc": "2.0",
+            "id": 1,
+            "result": 1,
+            "error": {"code": -32603, "message": "Internal error"}
+        });
+        assert!(serde_json::from_value::<JsonRpcResponse<u32>>(json).is_err());
+
+        let json = json!({"jsonrpc": "2.0", "id": 1, "result": 1, "error": null});
+        assert!(serde_json::from_value::<JsonRpcResponse<u32>>(json).is_err());
     }
 
     #[test]
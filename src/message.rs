@@ -0,0 +1,282 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+
+use crate::notification::is_notification;
+use crate::{
+    Batch, BatchEntry, ErrorObject, Id, InvalidEntry, JsonRpcNotification, JsonRpcRequest,
+    JsonRpcResponse,
+};
+
+/// Any JSON-RPC message that may arrive on a bidirectional connection.
+///
+/// Deserialization classifies an object by the members it carries: `method`
+/// with an `id` is a request, `method` without one is a notification, and
+/// `result` or `error` with an `id` is a response. Objects mixing `method`
+/// with `result` or `error`, or carrying none of them, are rejected. Elements
+/// of a batch are classified one by one, and those that fail are kept as
+/// [`InvalidEntry`] values.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum Message<P, R, E = ErrorObject> {
+    Request(JsonRpcRequest<P>),
+    Notification(JsonRpcNotification<P>),
+    Response(JsonRpcResponse<R, E>),
+    Batch(Batch<BatchEntry<Message<P, R, E>>>),
+}
+
+impl<P, R, E> Message<P, R, E> {
+    /// The id of a request or response; `None` for notifications and batches.
+    pub fn id(&self) -> Option<&Id> {
+        match self {
+            Self::Request(request) => Some(&request.header.id),
+            Self::Response(response) => Some(&response.0.header.id),
+            Self::Notification(_) | Self::Batch(_) => None,
+        }
+    }
+}
+
+impl<P, R, E> Message<P, R, E>
+where
+    P: DeserializeOwned,
+    R: DeserializeOwned,
+    E: DeserializeOwned,
+{
+    fn from_object(value: Value) -> Result<Self, String> {
+        let Some(object) = value.as_object() else {
+            return Err("expected a JSON-RPC message object".to_string());
+        };
+
+        let has_method = object.contains_key("method");
+        let has_outcome = object.contains_key("result") || object.contains_key("error");
+
+        match (has_method, has_outcome) {
+            (true, true) => Err(
+                "ambiguous message: `method` cannot be combined with `result` or `error`"
+                    .to_string(),
+            ),
+            (true, false) if is_notification(object) => JsonRpcNotification::deserialize(value)
+                .map(Self::Notification)
+                .map_err(|error| format!("invalid notification: {error}")),
+            (true, false) => JsonRpcRequest::deserialize(value)
+                .map(Self::Request)
+                .map_err(|error| format!("invalid request: {error}")),
+            (false, true) if object.contains_key("id") => JsonRpcResponse::deserialize(value)
+                .map(Self::Response)
+                .map_err(|error| format!("invalid response: {error}")),
+            (false, true) => Err("ambiguous message: response without an `id`".to_string()),
+            (false, false) => Err(
+                "not a JSON-RPC message: expected one of `method`, `result` or `error`".to_string(),
+            ),
+        }
+    }
+
+    /// Classifies a single message, keeping it as an [`InvalidEntry`] when it
+    /// isn't a valid one. Arrays are rejected, as batches don't nest.
+    pub(crate) fn from_entry(value: Value) -> BatchEntry<Self> {
+        let classified = if value.is_array() {
+            Err("nested batches are not allowed".to_string())
+        } else {
+            Self::from_object(value.clone())
+        };
+
+        match classified {
+            Ok(message) => BatchEntry::Valid(message),
+            Err(reason) => BatchEntry::Invalid(InvalidEntry::new(value, reason)),
+        }
+    }
+}
+
+impl<'de, P, R, E> Deserialize<'de> for Message<P, R, E>
+where
+    P: DeserializeOwned,
+    R: DeserializeOwned,
+    E: DeserializeOwned,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        match Value::deserialize(deserializer)? {
+            Value::Array(values) => {
+                let entries = values.into_iter().map(Self::from_entry).collect();
+                Batch::new(entries)
+                    .map(Self::Batch)
+                    .ok_or_else(|| D::Error::custom("empty batch is an Invalid Request"))
+            }
+            value => Self::from_object(value).map_err(D::Error::custom),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Header;
+    use serde_json::json;
+
+    #[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct TestPayload {
+        method: String,
+        #[serde(default)]
+        params: Vec<u32>,
+    }
+
+    type TestMessage = Message<TestPayload, u32>;
+
+    fn payload(method: &str, params: Vec<u32>) -> TestPayload {
+        TestPayload {
+            method: method.to_string(),
+            params,
+        }
+    }
+
+    #[test]
+    fn test_message_request() {
+        let json = json!({"jsonrpc": "2.0", "id": 1, "method": "sum", "params": [1, 2]});
+        let message: TestMessage = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            message,
+            Message::Request(JsonRpcRequest {
+                header: Header::v2(1),
+                payload: payload("sum", vec![1, 2]),
+            })
+        );
+        assert_eq!(message.id(), Some(&Id::from(1)));
+        assert_eq!(serde_json::to_value(&message).unwrap(), json);
+    }
+
+    #[test]
+    fn test_message_notification() {
+        let json = json!({"jsonrpc": "2.0", "method": "update", "params": [3]});
+        let message: TestMessage = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            message,
+            Message::Notification(JsonRpcNotification::v2(payload("update", vec![3])))
+        );
+        assert_eq!(message.id(), None);
+        assert_eq!(serde_json::to_value(&message).unwrap(), json);
+    }
+
+    #[test]
+    fn test_message_response() {
+        let json = json!({"jsonrpc": "2.0", "id": "a", "result": 3});
+        let message: TestMessage = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            message,
+            Message::Response(JsonRpcResponse::result(Header::v2("a"), 3))
+        );
+        assert_eq!(serde_json::to_value(&message).unwrap(), json);
+
+        let json = json!({
+            "jsonrpc": "2.0",
+            "id": null,
+            "error": {"code": -32700, "message": "Parse error"}
+        });
+        let message: TestMessage = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            message,
+            Message::Response(JsonRpcResponse::error(
+                Header::v2(Id::Null),
+                ErrorObject::parse_error()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_message_v1() {
+        let json = json!({"id": null, "method": "update", "params": [3]});
+        let message: TestMessage = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            message,
+            Message::Notification(JsonRpcNotification::v1(payload("update", vec![3])))
+        );
+        assert_eq!(serde_json::to_value(&message).unwrap(), json);
+
+        let json = json!({"id": 4, "method": "sum", "params": []});
+        let message: TestMessage = serde_json::from_value(json).unwrap();
+        assert!(matches!(message, Message::Request(_)));
+
+        let json = json!({"result": 4, "error": null, "id": 4});
+        let message: TestMessage = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            message,
+            Message::Response(JsonRpcResponse::result(Header::v1(4), 4))
+        );
+        assert_eq!(serde_json::to_value(&message).unwrap(), json);
+    }
+
+    #[test]
+    fn test_message_ambiguous() {
+        let json = json!({"jsonrpc": "2.0", "id": 1, "method": "sum", "result": 3});
+        let error = serde_json::from_value::<TestMessage>(json).unwrap_err();
+        assert!(error.to_string().contains("ambiguous"));
+
+        let json = json!({"jsonrpc": "2.0", "result": 3});
+        let error = serde_json::from_value::<TestMessage>(json).unwrap_err();
+        assert!(error.to_string().contains("without an `id`"));
+
+        let json = json!({"jsonrpc": "2.0", "id": 1});
+        let error = serde_json::from_value::<TestMessage>(json).unwrap_err();
+        assert!(error.to_string().contains("not a JSON-RPC message"));
+
+        let error = serde_json::from_value::<TestMessage>(json!("ping")).unwrap_err();
+        assert!(error.to_string().contains("object"));
+    }
+
+    #[test]
+    fn test_message_invalid_member_types() {
+        let json = json!({"jsonrpc": "2.0", "id": 1, "method": 5});
+        let error = serde_json::from_value::<TestMessage>(json).unwrap_err();
+        assert!(error.to_string().starts_with("invalid request"));
+
+        let json = json!({"jsonrpc": "2.0", "id": 1, "result": "three"});
+        let error = serde_json::from_value::<TestMessage>(json).unwrap_err();
+        assert!(error.to_string().starts_with("invalid response"));
+    }
+
+    #[test]
+    fn test_message_batch() {
+        let json = json!([
+            {"jsonrpc": "2.0", "id": 1, "method": "sum", "params": [1]},
+            {"jsonrpc": "2.0", "method": "update"},
+            {"jsonrpc": "2.0", "id": 2, "result": 7},
+            {"jsonrpc": "2.0", "id": 3, "method": "sum", "result": 3},
+            [{"jsonrpc": "2.0", "method": "update"}]
+        ]);
+
+        let message: TestMessage = serde_json::from_value(json).unwrap();
+        let Message::Batch(batch) = message else {
+            panic!("expected a batch");
+        };
+
+        let entries = batch.into_vec();
+        assert!(matches!(entries[0], BatchEntry::Valid(Message::Request(_))));
+        assert!(matches!(
+            entries[1],
+            BatchEntry::Valid(Message::Notification(_))
+        ));
+        assert!(matches!(
+            entries[2],
+            BatchEntry::Valid(Message::Response(_))
+        ));
+
+        let BatchEntry::Invalid(ambiguous) = &entries[3] else {
+            panic!("expected an invalid entry");
+        };
+        assert_eq!(ambiguous.id, Id::from(3));
+        assert!(ambiguous.reason.contains("ambiguous"));
+
+        let BatchEntry::Invalid(nested) = &entries[4] else {
+            panic!("expected an invalid entry");
+        };
+        assert_eq!(nested.id, Id::Null);
+        assert!(nested.reason.contains("nested"));
+    }
+
+    #[test]
+    fn test_message_empty_batch() {
+        let error = serde_json::from_value::<TestMessage>(json!([])).unwrap_err();
+        assert!(error.to_string().contains("Invalid Request"));
+    }
+}
@@ -0,0 +1,123 @@
+use crate::{Header, JsonRpcRequest, JsonRpcResponse, Request};
+
+/// Ties a method name to the types of its parameters, result and error, so
+/// that a request built for a method and the response decoded for it agree.
+pub trait Method {
+    const NAME: &'static str;
+    type Params;
+    type Result;
+    type Error;
+}
+
+/// A request for the method `M`.
+pub type MethodRequest<M> = JsonRpcRequest<Request<<M as Method>::Params>>;
+
+/// A response to a request for the method `M`.
+pub type MethodResponse<M> = JsonRpcResponse<<M as Method>::Result, <M as Method>::Error>;
+
+impl<P> JsonRpcRequest<Request<P>> {
+    pub fn for_method<M>(header: Header, params: P) -> Self
+    where
+        M: Method<Params = P>,
+    {
+        Self {
+            header,
+            payload: Request::new(M::NAME, params),
+        }
+    }
+}
+
+impl<P> Request<P> {
+    /// Whether this request targets the method `M`.
+    pub fn is<M>(&self) -> bool
+    where
+        M: Method,
+    {
+        self.method == M::NAME
+    }
+}
+
+impl<R, E> JsonRpcResponse<R, E> {
+    pub fn for_method<M>(header: Header, result: Result<R, E>) -> Self
+    where
+        M: Method<Result = R, Error = E>,
+    {
+        match result {
+            Ok(result) => Self::result(header, result),
+            Err(error) => Self::error(header, error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ErrorObject, Params};
+    use serde_json::json;
+
+    #[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct GreetParams {
+        name: String,
+    }
+
+    struct Greet;
+
+    impl Method for Greet {
+        const NAME: &'static str = "greet";
+        type Params = GreetParams;
+        type Result = String;
+        type Error = ErrorObject;
+    }
+
+    #[test]
+    fn test_method_request() {
+        let request: MethodRequest<Greet> = JsonRpcRequest::for_method::<Greet>(
+            Header::v2(1),
+            GreetParams {
+                name: "Ada".to_string(),
+            },
+        );
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "greet",
+                "params": {"name": "Ada"}
+            })
+        );
+
+        let untyped: JsonRpcRequest<Request<Params>> = serde_json::from_value(json).unwrap();
+        assert!(untyped.payload.is::<Greet>());
+        assert_eq!(
+            untyped.payload.parse_params::<GreetParams>().unwrap(),
+            request.payload.params.unwrap()
+        );
+    }
+
+    #[test]
+    fn test_method_response() {
+        let response: MethodResponse<Greet> =
+            JsonRpcResponse::for_method::<Greet>(Header::v2(1), Ok("Hello, Ada".to_string()));
+        let json = serde_json::to_value(&response).unwrap();
+        assert_eq!(
+            json,
+            json!({"jsonrpc": "2.0", "id": 1, "result": "Hello, Ada"})
+        );
+
+        let decoded: MethodResponse<Greet> = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.into_result(), Ok("Hello, Ada".to_string()));
+
+        let response: MethodResponse<Greet> =
+            JsonRpcResponse::for_method::<Greet>(Header::v2(2), Err(ErrorObject::invalid_params()));
+        assert_eq!(response.into_result(), Err(ErrorObject::invalid_params()));
+    }
+
+    #[test]
+    fn test_method_response_type_mismatch() {
+        let json = json!({"jsonrpc": "2.0", "id": 1, "result": 5});
+        assert!(serde_json::from_value::<MethodResponse<Greet>>(json).is_err());
+    }
+}
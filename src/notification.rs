@@ -0,0 +1,221 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use serde_json::{Map, Value};
+
+use crate::{Id, Version};
+
+/// A JSON-RPC notification: a request without an `id` member, to which the
+/// server must not reply.
+///
+/// Unlike a [`JsonRpcRequest`](crate::JsonRpcRequest) carrying a null id, a
+/// 2.0 notification omits the `id` member entirely, and deserialization
+/// rejects any object that has one. JSON-RPC 1.0 has no such distinction:
+/// there a notification carries `"id": null`.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct JsonRpcNotification<T> {
+    pub jsonrpc: Version,
+    pub payload: T,
+}
+
+impl<T> JsonRpcNotification<T> {
+    pub fn v1(payload: T) -> Self {
+        Self {
+            jsonrpc: Version::One,
+            payload,
+        }
+    }
+
+    pub fn v2(payload: T) -> Self {
+        Self {
+            jsonrpc: Version::Two,
+            payload,
+        }
+    }
+}
+
+/// Whether a message object is a notification rather than a request.
+pub(crate) fn is_notification(object: &Map<String, Value>) -> bool {
+    match object.get("id") {
+        None => true,
+        Some(Value::Null) => object.get("jsonrpc").is_none_or(|version| version == "1.0"),
+        Some(_) => false,
+    }
+}
+
+#[derive(Serialize)]
+struct NotificationReprRef<'a, T> {
+    #[serde(skip_serializing_if = "Version::is_one")]
+    jsonrpc: &'a Version,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<Id>,
+    #[serde(flatten)]
+    payload: &'a T,
+}
+
+impl<T> Serialize for JsonRpcNotification<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        NotificationReprRef {
+            jsonrpc: &self.jsonrpc,
+            id: self.jsonrpc.is_one().then_some(Id::Null),
+            payload: &self.payload,
+        }
+        .serialize(serializer)
+    }
+}
+
+#[derive(Deserialize)]
+struct NotificationRepr<T> {
+    #[serde(default = "Version::one")]
+    jsonrpc: Version,
+    #[serde(default, deserialize_with = "deserialize_present")]
+    id: Option<Id>,
+    #[serde(flatten)]
+    payload: T,
+}
+
+/// Maps a present member to `Some`, even when its value is `null`, so that a
+/// missing `id` can be told apart from an explicit null one.
+fn deserialize_present<'de, D>(deserializer: D) -> Result<Option<Id>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    Id::deserialize(deserializer).map(Some)
+}
+
+impl<'de, T> Deserialize<'de> for JsonRpcNotification<T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let repr = NotificationRepr::<T>::deserialize(deserializer)?;
+        match (&repr.jsonrpc, repr.id) {
+            (Version::One, None | Some(Id::Null)) | (Version::Two, None) => {}
+            (Version::One, Some(id)) => {
+                return Err(serde::de::Error::custom(format_args!(
+                    "1.0 notification must have a null `id`, found {id}"
+                )))
+            }
+            (Version::Two, Some(id)) => {
+                return Err(serde::de::Error::custom(format_args!(
+                    "notification must not have an `id` member, found {id}"
+                )))
+            }
+        }
+
+        Ok(Self {
+            jsonrpc: repr.jsonrpc,
+            payload: repr.payload,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, JsonRpcRequest};
+    use serde_json::{json, Value};
+
+    #[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+    struct TestPayload {
+        method: String,
+        params: Vec<u32>,
+    }
+
+    fn payload() -> TestPayload {
+        TestPayload {
+            method: "update".to_string(),
+            params: vec![1, 2],
+        }
+    }
+
+    #[test]
+    fn test_notification_serialization_omits_id() {
+        let notification = JsonRpcNotification::v2(payload());
+        let json = serde_json::to_value(&notification).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "jsonrpc": "2.0",
+                "method": "update",
+                "params": [1, 2]
+            })
+        );
+        assert!(!json.as_object().unwrap().contains_key("id"));
+    }
+
+    #[test]
+    fn test_notification_deserialization() {
+        let json = json!({"jsonrpc": "2.0", "method": "update", "params": [1, 2]});
+        let notification: JsonRpcNotification<TestPayload> = serde_json::from_value(json).unwrap();
+        assert_eq!(notification, JsonRpcNotification::v2(payload()));
+    }
+
+    #[test]
+    fn test_notification_rejects_id() {
+        let json = json!({"jsonrpc": "2.0", "id": null, "method": "update", "params": []});
+        let error = serde_json::from_value::<JsonRpcNotification<TestPayload>>(json).unwrap_err();
+        assert!(error.to_string().contains("`id`"));
+
+        let json = json!({"jsonrpc": "2.0", "id": 1, "method": "update", "params": []});
+        assert!(serde_json::from_value::<JsonRpcNotification<TestPayload>>(json).is_err());
+    }
+
+    #[test]
+    fn test_request_requires_id() {
+        let json = json!({"jsonrpc": "2.0", "method": "update", "params": [1, 2]});
+        assert!(serde_json::from_value::<JsonRpcRequest<TestPayload>>(json).is_err());
+
+        let json = json!({"jsonrpc": "2.0", "id": null, "method": "update", "params": [1, 2]});
+        let request: JsonRpcRequest<TestPayload> = serde_json::from_value(json).unwrap();
+        assert_eq!(request.header, Header::v2(Id::Null));
+    }
+
+    #[test]
+    fn test_v1_notification() {
+        let notification = JsonRpcNotification::v1(payload());
+        let json = serde_json::to_value(&notification).unwrap();
+        assert_eq!(
+            json,
+            json!({"id": null, "method": "update", "params": [1, 2]})
+        );
+
+        let deserialized: JsonRpcNotification<TestPayload> = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, notification);
+
+        let json = json!({"id": 1, "method": "update", "params": [1, 2]});
+        let error = serde_json::from_value::<JsonRpcNotification<TestPayload>>(json).unwrap_err();
+        assert!(error.to_string().contains("null `id`"));
+    }
+
+    #[test]
+    fn test_is_notification() {
+        let object = |value: Value| value.as_object().unwrap().clone();
+
+        assert!(is_notification(&object(
+            json!({"jsonrpc": "2.0", "method": "a"})
+        )));
+        assert!(!is_notification(&object(
+            json!({"jsonrpc": "2.0", "id": null, "method": "a"})
+        )));
+        assert!(is_notification(&object(json!({"id": null, "method": "a"}))));
+        assert!(is_notification(&object(
+            json!({"jsonrpc": "1.0", "id": null, "method": "a"})
+        )));
+        assert!(!is_notification(&object(json!({"id": 1, "method": "a"}))));
+    }
+
+    #[test]
+    fn test_notification_generic_payload() {
+        let json = json!({"jsonrpc": "2.0", "method": "exit"});
+        let notification: JsonRpcNotification<Value> = serde_json::from_value(json).unwrap();
+        assert_eq!(notification.payload, json!({"method": "exit"}));
+    }
+}
@@ -0,0 +1,286 @@
+use futures::channel::mpsc;
+use futures::stream::FuturesUnordered;
+use futures::{SinkExt, StreamExt};
+use serde_json::Value;
+
+use crate::server::{parse_error, to_message};
+use crate::transport::{Transport, TransportError};
+use crate::{Client, Handler, JsonRpcResponse, Reply, Server};
+
+/// Both ends of a JSON-RPC conversation over one transport, as LSP and
+/// similar protocols need: incoming requests and notifications go to a
+/// [`Server`], while incoming responses complete the calls made through
+/// [`Peer::client`].
+///
+/// Handlers find that [`Client`] in the extensions of their
+/// [`Context`](crate::Context), so they can call back into the remote side
+/// before answering. It cancels calls the same way as the server, and
+/// carries the [`Progress`](crate::Progress) handlers report.
+pub struct Peer<T, H> {
+    transport: T,
+    server: Server<H>,
+    client: Client,
+    outgoing: mpsc::UnboundedReceiver<Value>,
+}
+
+impl<T, H> Peer<T, H>
+where
+    T: Transport,
+    H: Handler,
+{
+    pub fn new(transport: T, server: Server<H>) -> Self {
+        let (client, outgoing) = Client::new();
+        let client = client.with_cancellation(server.cancellation().cloned());
+        let mut extensions = server.extensions().clone();
+        extensions.insert(client.clone());
+
+        Self {
+            transport,
+            server: server
+                .with_extensions(extensions)
+                .with_outgoing(client.outgoing()),
+            client,
+            outgoing,
+        }
+    }
+
+    /// The client making calls to the remote side. Clones of it may be used
+    /// from anywhere, as long as [`Peer::run`] is being polled.
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Runs the conversation until the remote side closes the transport.
+    ///
+    /// Incoming calls are handled concurrently, as [`Server::serve`] does.
+    /// Once the transport closes, pending calls to the remote side fail with
+    /// [`ClientError::Closed`](crate::ClientError::Closed), the handlers
+    /// still running are awaited, and the transport is closed.
+    pub async fn run(self) -> Result<(), TransportError> {
+        let Self {
+            transport,
+            server,
+            client,
+            mut outgoing,
+        } = self;
+        let (mut sink, stream) = transport.split();
+        let mut stream = stream.fuse();
+        let mut in_flight = FuturesUnordered::new();
+
+        let outcome = async {
+            loop {
+                futures::select! {
+                    message = stream.next() => match message {
+                        Some(Ok(message)) => {
+                            if let Some(calls) = resolve_responses(&client, message) {
+                                in_flight.push(server.handle_value(calls));
+                            }
+                        }
+                        Some(Err(TransportError::Parse(_))) => {
+                            sink.send(to_message(Reply::Single(parse_error()))).await?
+                        }
+                        Some(Err(TransportError::Unanswered { ids, error })) => {
+                            client.fail(&ids, error);
+                        }
+                        Some(Err(error)) => return Err(error),
+                        None => return Ok(()),
+                    },
+                    message = outgoing.select_next_some() => sink.send(message).await?,
+                    reply = in_flight.select_next_some() => {
+                        // The progress reported by a handler goes out before
+                        // its reply.
+                        while let Ok(message) = outgoing.try_recv() {
+                            sink.send(message).await?;
+                        }
+                        if let Some(reply) = reply {
+                            sink.send(to_message(reply)).await?;
+                        }
+                    }
+                }
+            }
+        }
+        .await;
+
+        // Nothing will answer calls to the remote side anymore, including
+        // those the remaining handlers might make.
+        outgoing.close();
+        client.close();
+        outcome?;
+
+        while let Some(reply) = in_flight.next().await {
+            if let Some(reply) = reply {
+                sink.send(to_message(reply)).await?;
+            }
+        }
+
+        sink.close().await
+    }
+}
+
+/// Hands the responses carried by `message` to `client`, returning whatever
+/// else the message carries for the server to handle.
+fn resolve_responses(client: &Client, message: Value) -> Option<Value> {
+    match message {
+        Value::Array(entries) if !entries.is_empty() => {
+            let calls: Vec<_> = entries
+                .into_iter()
+                .filter_map(|entry| resolve_response(client, entry))
+                .collect();
+            (!calls.is_empty()).then_some(Value::Array(calls))
+        }
+        message => resolve_response(client, message),
+    }
+}
+
+/// Hands `message` to `client` if it is a response, returning it otherwise.
+fn resolve_response(client: &Client, message: Value) -> Option<Value> {
+    let is_response = message.as_object().is_some_and(|object| {
+        !object.contains_key("method")
+            && (object.contains_key("result") || object.contains_key("error"))
+    });
+    if !is_response {
+        return Some(message);
+    }
+
+    // Responses are never answered, even when they are malformed.
+    if let Ok(response) = serde_json::from_value::<JsonRpcResponse<Value, Value>>(message) {
+        client.handle_response(response);
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Loopback;
+    use crate::{error_code, ClientError, Context, ErrorObject, Router};
+    use futures::executor::block_on;
+    use futures::future::{self, Either};
+    use futures::FutureExt;
+    use serde_json::json;
+
+    fn caller(context: &Context) -> Client {
+        context.extensions().get::<Client>().cloned().unwrap()
+    }
+
+    #[test]
+    fn test_peer_calls_back_during_a_request() {
+        // The editor answers `workspace/configuration` while its request to
+        // the language server is in flight.
+        let mut editor = Router::new();
+        editor.register("workspace/configuration", |_: ()| {
+            Ok::<_, ErrorObject>(json!({"tabSize": 4}))
+        });
+
+        let mut server = Router::new();
+        server.register_async(
+            "textDocument/formatting",
+            |_: (), context: Context| async move {
+                let configuration: Value = caller(&context)
+                    .request("workspace/configuration", ())
+                    .await
+                    .map_err(|_| ErrorObject::<Value>::internal_error())?;
+                Ok::<_, ErrorObject>(format!("indented by {}", configuration["tabSize"]))
+            },
+        );
+
+        let (editor_end, server_end) = Loopback::pair();
+        let editor = Peer::new(editor_end, Server::new(editor));
+        let server = Peer::new(server_end, Server::new(server));
+
+        let client = editor.client().clone();
+        let calls = async move {
+            let formatted = client.request::<_, String>("textDocument/formatting", ());
+            let unknown = client.request::<_, Value>("shutdown", ());
+            future::join(formatted, unknown).await
+        };
+
+        let running = future::join(editor.run(), server.run()).boxed();
+        let Either::Right(((formatted, unknown), _)) =
+            block_on(future::select(running, calls.boxed()))
+        else {
+            panic!("the peers should run until the calls complete");
+        };
+        assert_eq!(formatted.unwrap(), "indented by 4");
+        assert!(matches!(
+            unknown,
+            Err(ClientError::Rpc(error)) if error.code == error_code::METHOD_NOT_FOUND
+        ));
+    }
+
+    #[test]
+    fn test_peer_fails_pending_calls_when_closed() {
+        let (local, mut remote) = Loopback::pair();
+        let peer = Peer::new(local, Server::new(Router::new()));
+        let client = peer.client().clone();
+
+        let remote = async move {
+            let request = remote.next().await.unwrap().unwrap();
+            assert_eq!(request["method"], json!("initialize"));
+            assert_eq!(request.get("params"), None);
+
+            // A batch mixing a stray response and a call, then garbage.
+            let batch = json!([
+                {"jsonrpc": "2.0", "id": 99, "result": null},
+                {"jsonrpc": "2.0", "id": 1, "method": "ping"}
+            ]);
+            remote.send(batch).await.unwrap();
+            let reply = remote.next().await.unwrap().unwrap();
+            assert_eq!(reply[0]["id"], json!(1));
+            assert_eq!(reply.as_array().unwrap().len(), 1);
+        };
+
+        let (ran, response, ()) = block_on(future::join3(
+            peer.run(),
+            client.request::<_, Value>("initialize", ()),
+            remote,
+        ));
+        ran.unwrap();
+        assert!(matches!(response, Err(ClientError::Closed)));
+        assert!(matches!(
+            block_on(client.request::<_, Value>("initialize", ())),
+            Err(ClientError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_peer_fails_unanswered_calls_only() {
+        // Requests the remote side reports as `{"unanswered": id}` fail.
+        let (local, mut remote) = Loopback::pair();
+        let local = local.map(|message| {
+            let message = message?;
+            match message.get("unanswered") {
+                Some(id) => Err(TransportError::Unanswered {
+                    ids: vec![serde_json::from_value(id.clone()).unwrap()],
+                    error: std::io::ErrorKind::TimedOut.into(),
+                }),
+                None => Ok(message),
+            }
+        });
+        let peer = Peer::new(local, Server::new(Router::new()));
+        let client = peer.client().clone();
+
+        let remote = async move {
+            let first = remote.next().await.unwrap().unwrap();
+            let second = remote.next().await.unwrap().unwrap();
+            remote
+                .send(json!({"unanswered": first["id"]}))
+                .await
+                .unwrap();
+            let response = json!({"jsonrpc": "2.0", "id": second["id"], "result": 2});
+            remote.send(response).await.unwrap();
+        };
+
+        let calls = future::join(
+            client.request::<_, i64>("first", ()),
+            client.request::<_, i64>("second", ()),
+        );
+        let (ran, (first, second), ()) = block_on(future::join3(peer.run(), calls, remote));
+        ran.unwrap();
+        assert!(matches!(
+            first,
+            Err(ClientError::Transport(error)) if error.kind() == std::io::ErrorKind::TimedOut
+        ));
+        assert_eq!(second.unwrap(), 2);
+    }
+}
@@ -0,0 +1,212 @@
+//! JSON-RPC over the stdin and stdout of a child process, as spoken by
+//! language servers and similar tools.
+
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read};
+use std::process::{Child, ChildStdout, Command, ExitStatus, Stdio};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::Duration;
+
+use crate::blocking::BlockingIo;
+use crate::codec::{Codec, Framed};
+
+/// The stdin and stdout of a child process, framed by the codec `C`.
+pub type ProcessTransport<C> = Framed<BlockingIo, C>;
+
+/// The error ending the stream of a [`ProcessTransport`] once the process
+/// exits, wrapped in an [`io::Error`] of kind [`io::ErrorKind::BrokenPipe`].
+#[derive(Debug)]
+pub struct ProcessExited {
+    pub status: ExitStatus,
+}
+
+impl fmt::Display for ProcessExited {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "process exited with {}", self.status)
+    }
+}
+
+impl std::error::Error for ProcessExited {}
+
+/// Spawns `command` with its stdin and stdout framed by `codec`, passing
+/// every line it writes to stderr to `on_stderr`. Whatever stdio `command`
+/// was configured with is replaced by pipes.
+///
+/// Once stdout closes and the process exits, the stream fails with
+/// [`ProcessExited`], which a [`Client`](crate::Client) passes on to its
+/// pending requests as a [`ClientError::Transport`]. A process that closes
+/// its stdout but keeps running is checked on every 10ms until it exits.
+/// Closing the sink closes stdin, which most tools take as a signal to exit;
+/// a process still running when the transport is dropped is killed.
+///
+/// Stderr is read on a thread of its own, which runs until the process
+/// closes it, regardless of the transport: `on_stderr` may still be called
+/// after the stream has failed, or even after the transport is dropped.
+///
+/// [`ClientError::Transport`]: crate::ClientError::Transport
+pub fn spawn<C, F>(
+    command: &mut Command,
+    codec: C,
+    mut on_stderr: F,
+) -> io::Result<ProcessTransport<C>>
+where
+    C: Codec,
+    F: FnMut(&str) + Send + 'static,
+{
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let (Some(stdin), Some(stdout), Some(stderr)) =
+        (child.stdin.take(), child.stdout.take(), child.stderr.take())
+    else {
+        unreachable!("stdio is piped");
+    };
+
+    thread::spawn(move || {
+        let mut stderr = BufReader::new(stderr);
+        let mut line = Vec::new();
+        while matches!(stderr.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+            on_stderr(String::from_utf8_lossy(&line).trim_end_matches(['\r', '\n']));
+            line.clear();
+        }
+    });
+
+    let child = Arc::new(Mutex::new(child));
+    let stdout = Stdout {
+        stdout,
+        child: Arc::clone(&child),
+    };
+    let io = BlockingIo::new(stdout, stdin).on_drop(move || {
+        let mut child = child.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Ok(None) = child.try_wait() {
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+    });
+    Ok(Framed::new(io, codec))
+}
+
+/// How often the reader checks whether a process that closed its stdout has
+/// exited. It doesn't block in [`Child::wait`], which would keep the process
+/// from being killed when the transport is dropped.
+const EXIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The stdout of a child process, failing with [`ProcessExited`] instead of
+/// ending.
+struct Stdout {
+    stdout: ChildStdout,
+    child: Arc<Mutex<Child>>,
+}
+
+impl Read for Stdout {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.stdout.read(buf)? {
+            0 if !buf.is_empty() => loop {
+                let status = self
+                    .child
+                    .lock()
+                    .unwrap_or_else(PoisonError::into_inner)
+                    .try_wait()?;
+                match status {
+                    Some(status) => {
+                        break Err(io::Error::new(
+                            io::ErrorKind::BrokenPipe,
+                            ProcessExited { status },
+                        ))
+                    }
+                    None => thread::sleep(EXIT_POLL_INTERVAL),
+                }
+            },
+            read => Ok(read),
+        }
+    }
+}
+
+#[cfg(all(test, unix, feature = "client"))]
+mod tests {
+    use super::*;
+    use crate::codec::Ndjson;
+    use crate::transport::TransportError;
+    use crate::{Client, ClientError};
+    use futures::executor::block_on;
+    use futures::{future, SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_process_echo() {
+        let (sender, stderr) = mpsc::channel();
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo starting >&2; cat"]);
+        let mut transport = spawn(&mut command, Ndjson::new(), move |line| {
+            let _ = sender.send(line.to_string());
+        })
+        .unwrap();
+
+        let message = json!({"jsonrpc": "2.0", "method": "initialized", "params": {}});
+        block_on(transport.send(message.clone())).unwrap();
+        assert_eq!(block_on(transport.next()).unwrap().unwrap(), message);
+        assert_eq!(stderr.recv().unwrap(), "starting");
+
+        // `cat` exits once its stdin closes.
+        block_on(transport.close()).unwrap();
+        let Some(Err(TransportError::Io(error))) = block_on(transport.next()) else {
+            panic!("the stream should fail once the process exits");
+        };
+        let exited = error.get_ref().unwrap().downcast_ref::<ProcessExited>();
+        assert!(exited.unwrap().status.success());
+        assert!(block_on(transport.next()).is_none());
+    }
+
+    #[test]
+    fn test_process_exit_fails_pending_requests() {
+        let mut command = Command::new("sh");
+        command.args(["-c", "read request; exit 3"]);
+        let transport = spawn(&mut command, Ndjson::new(), |_| {}).unwrap();
+
+        let (client, driver) = Client::connect(transport);
+        let calls = async move { client.request::<_, Value>("initialize", json!({})).await };
+        let (driven, response) = block_on(future::join(driver, calls));
+
+        let Err(ClientError::Transport(error)) = response else {
+            panic!("pending requests should fail once the process exits");
+        };
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        let exited = error.get_ref().unwrap().downcast_ref::<ProcessExited>();
+        assert_eq!(exited.unwrap().status.code(), Some(3));
+
+        let Err(TransportError::Io(error)) = driven else {
+            panic!("the driver should fail once the process exits");
+        };
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(error.to_string(), "process exited with exit status: 3");
+    }
+
+    #[test]
+    fn test_process_killed_after_closing_stdout() {
+        let (sender, stderr) = mpsc::channel();
+        let mut command = Command::new("sh");
+        command.args(["-c", "exec 1>&-; echo $$ >&2; exec sleep 30"]);
+        let mut transport = spawn(&mut command, Ndjson::new(), move |line| {
+            let _ = sender.send(line.to_string());
+        })
+        .unwrap();
+        let pid = stderr.recv().unwrap();
+
+        // Gives the reader time to see stdout close while the process keeps
+        // running.
+        assert!(block_on(async { futures::poll!(transport.next()) }).is_pending());
+        thread::sleep(Duration::from_millis(50));
+        drop(transport);
+
+        let alive = Command::new("kill")
+            .args(["-0", &pid])
+            .stderr(Stdio::null())
+            .status()
+            .unwrap();
+        assert!(!alive.success());
+    }
+}
@@ -0,0 +1,233 @@
+use futures::channel::mpsc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::transport::TransportError;
+use crate::{Id, JsonRpcNotification, Params, Request, Version};
+
+/// The params of a `$/progress` notification, reporting on the work started
+/// by the request that carried `token`.
+///
+/// Requests opt in the way LSP defines: their named params carry the token
+/// as `workDoneToken`. Handlers report through the [`Progress`] of their
+/// [`Context`](crate::Context).
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+pub struct ProgressParams {
+    pub token: Id,
+    pub value: ProgressValue,
+}
+
+impl ProgressParams {
+    pub const METHOD: &'static str = "$/progress";
+}
+
+/// The stages of the work a request started: one `Begin`, any number of
+/// `Report`s, then one `End`. Percentages go from 0 to 100.
+#[derive(Clone, Debug, Eq, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum ProgressValue {
+    Begin {
+        title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        percentage: Option<u32>,
+    },
+    Report {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        percentage: Option<u32>,
+    },
+    End {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message: Option<String>,
+    },
+}
+
+/// Reports the progress of the request being handled to the client that
+/// sent it, as `$/progress` notifications carrying the token of the request,
+/// in the JSON-RPC version of the request.
+///
+/// Notifications sent before the handler returns reach the client before
+/// the response. Sending fails with [`TransportError::Closed`] once the
+/// connection is gone.
+#[derive(Clone, Debug)]
+pub struct Progress {
+    token: Id,
+    version: Version,
+    outgoing: mpsc::UnboundedSender<Value>,
+}
+
+impl Progress {
+    pub(crate) fn new(token: Id, version: Version, outgoing: mpsc::UnboundedSender<Value>) -> Self {
+        Self {
+            token,
+            version,
+            outgoing,
+        }
+    }
+
+    pub fn token(&self) -> &Id {
+        &self.token
+    }
+
+    pub fn begin(&self, title: &str) -> Result<(), TransportError> {
+        self.send(ProgressValue::Begin {
+            title: title.to_string(),
+            message: None,
+            percentage: None,
+        })
+    }
+
+    pub fn report(
+        &self,
+        message: Option<&str>,
+        percentage: Option<u32>,
+    ) -> Result<(), TransportError> {
+        self.send(ProgressValue::Report {
+            message: message.map(str::to_string),
+            percentage,
+        })
+    }
+
+    pub fn end(&self, message: Option<&str>) -> Result<(), TransportError> {
+        self.send(ProgressValue::End {
+            message: message.map(str::to_string),
+        })
+    }
+
+    pub fn send(&self, value: ProgressValue) -> Result<(), TransportError> {
+        let params = ProgressParams {
+            token: self.token.clone(),
+            value,
+        };
+        let notification = JsonRpcNotification {
+            jsonrpc: self.version.clone(),
+            payload: Request::new(ProgressParams::METHOD, params),
+        };
+        let message = serde_json::to_value(notification).expect("progress always serializes");
+        self.outgoing
+            .unbounded_send(message)
+            .map_err(|_| TransportError::Closed)
+    }
+}
+
+/// The progress token carried by `request`, if any.
+pub(crate) fn token(request: &Request) -> Option<Id> {
+    let Some(Params::Object(params)) = &request.params else {
+        return None;
+    };
+    params
+        .get("workDoneToken")
+        .and_then(|token| Id::deserialize(token).ok())
+        .filter(|token| !token.is_null())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::transport::Loopback;
+    use crate::{Context, ErrorObject, Router, Server};
+    use futures::executor::block_on;
+    use futures::{future, SinkExt, StreamExt};
+    use serde_json::json;
+
+    #[test]
+    fn test_progress_params_serialization() {
+        let params = ProgressParams {
+            token: Id::from("indexing"),
+            value: ProgressValue::Report {
+                message: None,
+                percentage: Some(50),
+            },
+        };
+        let value = json!({"token": "indexing", "value": {"kind": "report", "percentage": 50}});
+        assert_eq!(serde_json::to_value(&params).unwrap(), value);
+        assert_eq!(
+            serde_json::from_value::<ProgressParams>(value).unwrap(),
+            params
+        );
+
+        let end = json!({"token": 1, "value": {"kind": "end"}});
+        let params: ProgressParams = serde_json::from_value(end).unwrap();
+        assert_eq!(params.value, ProgressValue::End { message: None });
+        assert!(serde_json::from_value::<ProgressValue>(json!({"kind": "begin"})).is_err());
+    }
+
+    #[test]
+    fn test_server_reports_progress_over_loopback() {
+        let mut router = Router::new();
+        router.register_async("index", |_: Value, context: Context| async move {
+            let Some(progress) = context.progress() else {
+                return Ok::<_, ErrorObject>("untracked");
+            };
+            progress.begin("Indexing").unwrap();
+            progress.report(None, Some(50)).unwrap();
+            progress.end(Some("Done")).unwrap();
+            Ok("tracked")
+        });
+        let (server_end, mut client_end) = Loopback::pair();
+
+        let client = async move {
+            let request = json!({
+                "jsonrpc": "2.0",
+                "method": "index",
+                "params": {"workDoneToken": "t"},
+                "id": 1
+            });
+            client_end.send(request).await.unwrap();
+
+            let mut values = Vec::new();
+            for _ in 0..3 {
+                let notification = client_end.next().await.unwrap().unwrap();
+                assert_eq!(notification["method"], json!(ProgressParams::METHOD));
+                let params: ProgressParams =
+                    serde_json::from_value(notification["params"].clone()).unwrap();
+                assert_eq!(params.token, "t".into());
+                values.push(params.value);
+            }
+            assert_eq!(
+                values,
+                [
+                    ProgressValue::Begin {
+                        title: "Indexing".into(),
+                        message: None,
+                        percentage: None
+                    },
+                    ProgressValue::Report {
+                        message: None,
+                        percentage: Some(50)
+                    },
+                    ProgressValue::End {
+                        message: Some("Done".into())
+                    },
+                ]
+            );
+            let reply = client_end.next().await.unwrap().unwrap();
+            assert_eq!(reply["result"], json!("tracked"));
+
+            let request = json!({"jsonrpc": "2.0", "method": "index", "id": 2});
+            client_end.send(request).await.unwrap();
+            let reply = client_end.next().await.unwrap().unwrap();
+            assert_eq!(reply["result"], json!("untracked"));
+
+            // A 1.0 request gets 1.0 notifications.
+            let request = json!({"method": "index", "params": {"workDoneToken": "u"}, "id": 3});
+            client_end.send(request).await.unwrap();
+            let notification = client_end.next().await.unwrap().unwrap();
+            assert_eq!(notification.get("jsonrpc"), None);
+            assert_eq!(notification["id"], Value::Null);
+            assert_eq!(notification["method"], json!(ProgressParams::METHOD));
+            for _ in 0..2 {
+                client_end.next().await.unwrap().unwrap();
+            }
+            let reply = client_end.next().await.unwrap().unwrap();
+            assert_eq!(reply["result"], json!("tracked"));
+        };
+
+        let server = Server::new(router);
+        let (served, ()) = block_on(future::join(server.serve(server_end), client));
+        served.unwrap();
+    }
+}
@@ -0,0 +1,240 @@
+use serde::de::{DeserializeOwned, Error as _};
+use serde::{Deserialize, Deserializer};
+use serde_json::{Map, Value};
+
+/// The `params` member of a request: positional (an array) or named (an
+/// object).
+#[derive(Clone, Debug, Eq, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum Params {
+    Array(Vec<Value>),
+    Object(Map<String, Value>),
+}
+
+impl Params {
+    pub fn is_array(&self) -> bool {
+        matches!(self, Self::Array(_))
+    }
+
+    pub fn is_object(&self) -> bool {
+        matches!(self, Self::Object(_))
+    }
+
+    /// Deserializes the parameters into `T`, whichever form they were sent in.
+    ///
+    /// Positional parameters fill the fields of a struct in declaration
+    /// order, named parameters fill them by name. Tuples and sequences only
+    /// accept positional parameters.
+    pub fn parse<T>(&self) -> Result<T, serde_json::Error>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_value(self.clone().into())
+    }
+}
+
+impl From<Vec<Value>> for Params {
+    fn from(params: Vec<Value>) -> Self {
+        Self::Array(params)
+    }
+}
+
+impl From<Map<String, Value>> for Params {
+    fn from(params: Map<String, Value>) -> Self {
+        Self::Object(params)
+    }
+}
+
+impl From<Params> for Value {
+    fn from(params: Params) -> Self {
+        match params {
+            Params::Array(params) => Value::Array(params),
+            Params::Object(params) => Value::Object(params),
+        }
+    }
+}
+
+impl TryFrom<Value> for Params {
+    type Error = Value;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Array(params) => Ok(Self::Array(params)),
+            Value::Object(params) => Ok(Self::Object(params)),
+            value => Err(value),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Params {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::try_from(Value::deserialize(deserializer)?)
+            .map_err(|_| D::Error::custom("params must be an array or an object"))
+    }
+}
+
+/// The payload of a request or notification: the method to invoke and its
+/// optional parameters.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct Request<P = Params> {
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<P>,
+}
+
+impl<P> Request<P> {
+    pub fn new(method: impl Into<String>, params: P) -> Self {
+        Self {
+            method: method.into(),
+            params: Some(params),
+        }
+    }
+
+    pub fn without_params(method: impl Into<String>) -> Self {
+        Self {
+            method: method.into(),
+            params: None,
+        }
+    }
+}
+
+impl Request<Params> {
+    /// Deserializes the parameters into `T`, accepting both positional and
+    /// named parameters. Missing parameters are read as `null`.
+    pub fn parse_params<T>(&self) -> Result<T, serde_json::Error>
+    where
+        T: DeserializeOwned,
+    {
+        match &self.params {
+            Some(params) => params.parse(),
+            None => serde_json::from_value(Value::Null),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Header, JsonRpcRequest};
+    use serde_json::json;
+
+    #[derive(Debug, Eq, PartialEq, serde::Deserialize)]
+    struct Subtract {
+        minuend: i64,
+        subtrahend: i64,
+    }
+
+    #[test]
+    fn test_params_deserialization() {
+        let params: Params = serde_json::from_value(json!([1, "a"])).unwrap();
+        assert_eq!(params, Params::Array(vec![json!(1), json!("a")]));
+        assert!(params.is_array());
+
+        let params: Params = serde_json::from_value(json!({"a": 1})).unwrap();
+        assert!(params.is_object());
+
+        let error = serde_json::from_value::<Params>(json!(1)).unwrap_err();
+        assert!(error.to_string().contains("array or an object"));
+        assert!(serde_json::from_value::<Params>(json!("a")).is_err());
+    }
+
+    #[test]
+    fn test_params_parse_both_forms() {
+        let positional: Params = serde_json::from_value(json!([42, 23])).unwrap();
+        let named: Params =
+            serde_json::from_value(json!({"subtrahend": 23, "minuend": 42})).unwrap();
+
+        let expected = Subtract {
+            minuend: 42,
+            subtrahend: 23,
+        };
+        assert_eq!(positional.parse::<Subtract>().unwrap(), expected);
+        assert_eq!(named.parse::<Subtract>().unwrap(), expected);
+    }
+
+    #[test]
+    fn test_params_parse_errors() {
+        let params: Params = serde_json::from_value(json!([42])).unwrap();
+        assert!(params.parse::<Subtract>().is_err());
+
+        let params: Params = serde_json::from_value(json!([42, 23, 1])).unwrap();
+        assert!(params.parse::<Subtract>().is_err());
+
+        let params: Params = serde_json::from_value(json!({"minuend": 42})).unwrap();
+        assert!(params.parse::<Subtract>().is_err());
+    }
+
+    #[test]
+    fn test_params_parse_tuple() {
+        let params: Params = serde_json::from_value(json!([1, "two"])).unwrap();
+        let parsed: (u32, String) = params.parse().unwrap();
+        assert_eq!(parsed, (1, "two".to_string()));
+    }
+
+    #[test]
+    fn test_request_serialization() {
+        let request = JsonRpcRequest {
+            header: Header::v2(1),
+            payload: Request::new("subtract", Params::Array(vec![json!(42), json!(23)])),
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(
+            json,
+            json!({"jsonrpc": "2.0", "id": 1, "method": "subtract", "params": [42, 23]})
+        );
+
+        let deserialized: JsonRpcRequest<Request> = serde_json::from_value(json).unwrap();
+        assert_eq!(deserialized, request);
+
+        let request = Request::<Params>::without_params("ping");
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json, json!({"method": "ping"}));
+    }
+
+    #[test]
+    fn test_request_typed_params() {
+        #[derive(Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+        struct Greet {
+            name: String,
+        }
+
+        let json = json!({"method": "greet", "params": {"name": "Ada"}});
+        let request: Request<Greet> = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            request,
+            Request::new(
+                "greet",
+                Greet {
+                    name: "Ada".to_string()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_request_parse_params() {
+        let json = json!({"method": "subtract", "params": {"minuend": 3, "subtrahend": 1}});
+        let request: Request = serde_json::from_value(json).unwrap();
+        assert_eq!(
+            request.parse_params::<Subtract>().unwrap(),
+            Subtract {
+                minuend: 3,
+                subtrahend: 1
+            }
+        );
+
+        let request: Request = serde_json::from_value(json!({"method": "ping"})).unwrap();
+        assert_eq!(request.params, None);
+        request.parse_params::<()>().unwrap();
+        assert!(request.parse_params::<Subtract>().is_err());
+    }
+
+    #[test]
+    fn test_request_rejects_scalar_params() {
+        let json = json!({"method": "subtract", "params": 5});
+        assert!(serde_json::from_value::<Request>(json).is_err());
+    }
+}
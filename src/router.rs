@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use futures::future::{self, BoxFuture, FutureExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::server::{Context, Handler};
+use crate::{ErrorObject, JsonRpcNotification, JsonRpcRequest, JsonRpcResponse, Method, Request};
+
+type BoxedHandler =
+    Box<dyn Fn(Request, Context) -> BoxFuture<'static, Result<Value, ErrorObject>> + Send + Sync>;
+
+/// Dispatches requests to handlers registered by method name.
+///
+/// Parameters are decoded into the handler's argument type, accepting both
+/// positional and named parameters. Unknown methods are answered with a
+/// Method Not Found error, and parameters that fail to decode with an
+/// Invalid Params error.
+#[derive(Default)]
+pub struct Router {
+    handlers: HashMap<String, BoxedHandler>,
+}
+
+impl Router {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `handler` for `method`, replacing any previous handler.
+    ///
+    /// The error returned by the handler must serialize as an error object;
+    /// errors that don't are reported as an Internal Error.
+    pub fn register<P, R, E, F>(&mut self, method: impl Into<String>, handler: F) -> &mut Self
+    where
+        P: DeserializeOwned,
+        R: Serialize,
+        E: Serialize,
+        F: Fn(P) -> Result<R, E> + Send + Sync + 'static,
+    {
+        let handler = move |request: Request, _: Context| {
+            let outcome = parse_params(&request).and_then(|params| to_outcome(handler(params)));
+            future::ready(outcome).boxed()
+        };
+
+        self.handlers.insert(method.into(), Box::new(handler));
+        self
+    }
+
+    /// Registers the asynchronous `handler` for `method`, replacing any
+    /// previous handler.
+    pub fn register_async<P, R, E, F, Fut>(
+        &mut self,
+        method: impl Into<String>,
+        handler: F,
+    ) -> &mut Self
+    where
+        P: DeserializeOwned,
+        R: Serialize + 'static,
+        E: Serialize + 'static,
+        F: Fn(P, Context) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<R, E>> + Send + 'static,
+    {
+        let handler = move |request: Request, context: Context| match parse_params(&request) {
+            Ok(params) => handler(params, context).map(to_outcome).boxed(),
+            Err(error) => future::ready(Err(error)).boxed(),
+        };
+
+        self.handlers.insert(method.into(), Box::new(handler));
+        self
+    }
+
+    /// Registers `handler` for the method `M`.
+    pub fn register_method<M>(
+        &mut self,
+        handler: impl Fn(M::Params) -> Result<M::Result, M::Error> + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        M: Method,
+        M::Params: DeserializeOwned,
+        M::Result: Serialize,
+        M::Error: Serialize,
+    {
+        self.register(M::NAME, handler)
+    }
+
+    /// Registers the asynchronous `handler` for the method `M`.
+    pub fn register_method_async<M, Fut>(
+        &mut self,
+        handler: impl Fn(M::Params, Context) -> Fut + Send + Sync + 'static,
+    ) -> &mut Self
+    where
+        M: Method,
+        M::Params: DeserializeOwned,
+        M::Result: Serialize + 'static,
+        M::Error: Serialize + 'static,
+        Fut: Future<Output = Result<M::Result, M::Error>> + Send + 'static,
+    {
+        self.register_async(M::NAME, handler)
+    }
+
+    pub fn contains(&self, method: &str) -> bool {
+        self.handlers.contains_key(method)
+    }
+
+    /// Handles `request`, echoing its header back in the response.
+    pub async fn handle(&self, request: JsonRpcRequest<Request>) -> JsonRpcResponse<Value> {
+        let JsonRpcRequest { header, payload } = request;
+        let context = Context::request(header.id.clone());
+        match self.dispatch(payload, context).await {
+            Ok(result) => JsonRpcResponse::result(header, result),
+            Err(error) => JsonRpcResponse::error(header, error),
+        }
+    }
+
+    /// Handles `notification`, discarding whatever the handler returns.
+    pub async fn notify(&self, notification: JsonRpcNotification<Request>) {
+        let _ = self
+            .dispatch(notification.payload, Context::notification())
+            .await;
+    }
+
+    fn dispatch(
+        &self,
+        request: Request,
+        context: Context,
+    ) -> BoxFuture<'static, Result<Value, ErrorObject>> {
+        match self.handlers.get(&request.method) {
+            Some(handler) => handler(request, context),
+            None => {
+                let error = ErrorObject::method_not_found().with_data(request.method.into());
+                future::ready(Err(error)).boxed()
+            }
+        }
+    }
+}
+
+impl Handler for Router {
+    fn call(
+        &self,
+        request: Request,
+        context: Context,
+    ) -> BoxFuture<'_, Result<Value, ErrorObject>> {
+        self.dispatch(request, context)
+    }
+}
+
+fn parse_params<P>(request: &Request) -> Result<P, ErrorObject>
+where
+    P: DeserializeOwned,
+{
+    request
+        .parse_params()
+        .map_err(|error| ErrorObject::invalid_params().with_data(error.to_string().into()))
+}
+
+fn to_outcome<R, E>(outcome: Result<R, E>) -> Result<Value, ErrorObject>
+where
+    R: Serialize,
+    E: Serialize,
+{
+    match outcome {
+        Ok(result) => serde_json::to_value(result)
+            .map_err(|error| ErrorObject::internal_error().with_data(error.to_string().into())),
+        Err(error) => Err(to_error_object(error)),
+    }
+}
+
+fn to_error_object<E>(error: E) -> ErrorObject
+where
+    E: Serialize,
+{
+    serde_json::to_value(error)
+        .and_then(serde_json::from_value)
+        .unwrap_or_else(|error| ErrorObject::internal_error().with_data(error.to_string().into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{error_code, Header, Id, Params};
+    use futures::executor::block_on;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    #[derive(serde::Deserialize)]
+    struct Subtract {
+        minuend: i64,
+        subtrahend: i64,
+    }
+
+    struct Greet;
+
+    impl Method for Greet {
+        const NAME: &'static str = "greet";
+        type Params = (String,);
+        type Result = String;
+        type Error = ErrorObject<()>;
+    }
+
+    fn router() -> Router {
+        let mut router = Router::new();
+        router
+            .register("subtract", |params: Subtract| {
+                Ok::<_, ErrorObject>(params.minuend - params.subtrahend)
+            })
+            .register("fail", |_: ()| {
+                Err::<(), _>(
+                    ErrorObject::server_error(-32001, "Busy")
+                        .unwrap()
+                        .with_data(3),
+                )
+            })
+            .register_method::<Greet>(|(name,)| Ok(format!("Hello, {name}")));
+        router
+    }
+
+    fn request(id: impl Into<Id>, value: Value) -> JsonRpcRequest<Request> {
+        JsonRpcRequest {
+            header: Header::v2(id),
+            payload: serde_json::from_value(value).unwrap(),
+        }
+    }
+
+    #[test]
+    fn test_router_dispatch() {
+        let router = router();
+        assert!(router.contains("subtract"));
+        assert!(!router.contains("add"));
+
+        let response = block_on(router.handle(request(
+            1,
+            json!({"method": "subtract", "params": [42, 23]}),
+        )));
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), json!(19)));
+
+        let response = block_on(router.handle(request(
+            "2",
+            json!({"method": "subtract", "params": {"subtrahend": 23, "minuend": 42}}),
+        )));
+        assert_eq!(
+            response,
+            JsonRpcResponse::result(Header::v2("2"), json!(19))
+        );
+
+        let response =
+            block_on(router.handle(request(3, json!({"method": "greet", "params": ["Ada"]}))));
+        assert_eq!(
+            response,
+            JsonRpcResponse::result(Header::v2(3), json!("Hello, Ada"))
+        );
+    }
+
+    #[test]
+    fn test_router_echoes_v1_header() {
+        let router = router();
+        let request = JsonRpcRequest {
+            header: Header::v1("a"),
+            payload: Request::new("subtract", Params::Array(vec![json!(2), json!(1)])),
+        };
+
+        let response = block_on(router.handle(request));
+        assert_eq!(response.0.header, Header::v1("a"));
+    }
+
+    #[test]
+    fn test_router_method_not_found() {
+        let response =
+            block_on(router().handle(request(1, json!({"method": "add", "params": [1]}))));
+        let error = response.into_result().unwrap_err();
+        assert_eq!(error.code, error_code::METHOD_NOT_FOUND);
+        assert_eq!(error.data, Some(json!("add")));
+    }
+
+    #[test]
+    fn test_router_invalid_params() {
+        let router = router();
+
+        let response =
+            block_on(router.handle(request(1, json!({"method": "subtract", "params": [1]}))));
+        let error = response.into_result().unwrap_err();
+        assert_eq!(error.code, error_code::INVALID_PARAMS);
+        assert!(error.data.is_some());
+
+        let response = block_on(router.handle(request(2, json!({"method": "subtract"}))));
+        let error = response.into_result().unwrap_err();
+        assert_eq!(error.code, error_code::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_router_handler_error() {
+        let response = block_on(router().handle(request(1, json!({"method": "fail"}))));
+        assert_eq!(
+            response.into_result(),
+            Err(ErrorObject::server_error(-32001, "Busy")
+                .unwrap()
+                .with_data(json!(3)))
+        );
+    }
+
+    #[test]
+    fn test_router_handler_error_not_an_error_object() {
+        let mut router = Router::new();
+        router.register("odd", |_: ()| Err::<(), _>("not an object"));
+
+        let response = block_on(router.handle(request(1, json!({"method": "odd"}))));
+        let error = response.into_result().unwrap_err();
+        assert_eq!(error.code, error_code::INTERNAL_ERROR);
+    }
+
+    #[test]
+    fn test_router_async_handler() {
+        let mut router = Router::new();
+        router.register_async("delayed", |(value,): (u32,), context: Context| async move {
+            future::ready(()).await;
+            Ok::<_, ErrorObject>(json!({"value": value, "id": context.id()}))
+        });
+
+        let response =
+            block_on(router.handle(request(7, json!({"method": "delayed", "params": [3]}))));
+        assert_eq!(response.into_result(), Ok(json!({"value": 3, "id": 7})));
+
+        let response =
+            block_on(router.handle(request(8, json!({"method": "delayed", "params": {}}))));
+        let error = response.into_result().unwrap_err();
+        assert_eq!(error.code, error_code::INVALID_PARAMS);
+    }
+
+    #[test]
+    fn test_router_notify() {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let mut router = Router::new();
+        router.register("tick", {
+            let calls = calls.clone();
+            move |_: ()| {
+                calls.fetch_add(1, Ordering::SeqCst);
+                Ok::<_, ErrorObject>(())
+            }
+        });
+
+        block_on(router.notify(JsonRpcNotification::v2(Request::without_params("tick"))));
+        block_on(router.notify(JsonRpcNotification::v2(Request::without_params("missing"))));
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
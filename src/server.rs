@@ -0,0 +1,858 @@
+use std::collections::HashMap;
+use std::io;
+use std::pin::pin;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use futures::channel::mpsc;
+use futures::future::{self, AbortHandle, Abortable, BoxFuture};
+use futures::stream::FuturesUnordered;
+use futures::{SinkExt, Stream, StreamExt};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::transport::{Connection, Transport, TransportError};
+use crate::{
+    progress, Batch, BatchEntry, Cancellation, ErrorObject, Extensions, Header, Id, JsonRpcRequest,
+    JsonRpcResponse, Message, Progress, Request, Version,
+};
+
+/// Context of the call being handled.
+#[derive(Clone, Debug)]
+pub struct Context {
+    id: Option<Id>,
+    extensions: Extensions,
+    abort_handle: Option<AbortHandle>,
+    progress: Option<Progress>,
+}
+
+impl Context {
+    pub fn request(id: Id) -> Self {
+        Self {
+            id: Some(id),
+            extensions: Extensions::new(),
+            abort_handle: None,
+            progress: None,
+        }
+    }
+
+    pub fn notification() -> Self {
+        Self {
+            id: None,
+            extensions: Extensions::new(),
+            abort_handle: None,
+            progress: None,
+        }
+    }
+
+    pub fn with_extensions(mut self, extensions: Extensions) -> Self {
+        self.extensions = extensions;
+        self
+    }
+
+    /// The id of the request being handled; `None` for notifications.
+    pub fn id(&self) -> Option<&Id> {
+        self.id.as_ref()
+    }
+
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+
+    /// What is known about the connection the call came in on.
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Whether the client cancelled the request. The handler future is
+    /// dropped at its next await point anyway; this lets work running
+    /// elsewhere, such as on another thread, notice and stop early.
+    pub fn is_cancelled(&self) -> bool {
+        self.abort_handle
+            .as_ref()
+            .is_some_and(AbortHandle::is_aborted)
+    }
+
+    /// Reports the progress of the request to the client; `None` unless the
+    /// request carries a progress token and the server can send
+    /// notifications. Only requests served by [`Server::serve`] or a `Peer`
+    /// can: those handled through [`Server::handle_value`] and its siblings,
+    /// or by an `HttpServer`, have no connection to send them on.
+    pub fn progress(&self) -> Option<&Progress> {
+        self.progress.as_ref()
+    }
+}
+
+/// Handles the calls received by a [`Server`].
+///
+/// The same method handles requests and notifications; the result of a
+/// notification is discarded.
+pub trait Handler: Send + Sync + 'static {
+    fn call(&self, request: Request, context: Context)
+        -> BoxFuture<'_, Result<Value, ErrorObject>>;
+}
+
+impl<H> Handler for Arc<H>
+where
+    H: Handler + ?Sized,
+{
+    fn call(
+        &self,
+        request: Request,
+        context: Context,
+    ) -> BoxFuture<'_, Result<Value, ErrorObject>> {
+        (**self).call(request, context)
+    }
+}
+
+/// What a [`Server`] sends back for an incoming message.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+#[serde(untagged)]
+pub enum Reply {
+    Single(JsonRpcResponse<Value>),
+    Batch(Batch<JsonRpcResponse<Value>>),
+}
+
+impl Reply {
+    pub fn into_responses(self) -> Vec<JsonRpcResponse<Value>> {
+        match self {
+            Self::Single(response) => vec![response],
+            Self::Batch(batch) => batch.into_vec(),
+        }
+    }
+}
+
+/// Processes raw JSON-RPC messages end to end: classifies them, runs the
+/// handler, and aggregates the replies.
+///
+/// Batches follow the specification: their elements run concurrently,
+/// invalid elements get their own Invalid Request response, and a batch made
+/// only of notifications produces no reply.
+///
+/// Requests can be cancelled as described by [`Cancellation`], which is
+/// enabled with its defaults. Cancellations only reach requests handled by
+/// the same server or its clones, or, under [`Server::serve`], by the same
+/// session.
+pub struct Server<H> {
+    handler: Arc<H>,
+    extensions: Extensions,
+    cancellation: Option<Cancellation>,
+    running: Running,
+    outgoing: Option<mpsc::UnboundedSender<Value>>,
+}
+
+type Running = Arc<Mutex<RunningRequests>>;
+
+/// The abort handles of the requests being handled, by id. Each request gets
+/// a generation of its own, so that one finishing doesn't unregister another
+/// that shares its id.
+#[derive(Default)]
+struct RunningRequests {
+    next_generation: u64,
+    requests: HashMap<Id, (u64, AbortHandle)>,
+}
+
+impl<H> Clone for Server<H> {
+    fn clone(&self) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            extensions: self.extensions.clone(),
+            cancellation: self.cancellation.clone(),
+            running: self.running.clone(),
+            outgoing: self.outgoing.clone(),
+        }
+    }
+}
+
+impl<H> Server<H>
+where
+    H: Handler,
+{
+    pub fn new(handler: H) -> Self {
+        Self {
+            handler: Arc::new(handler),
+            extensions: Extensions::new(),
+            cancellation: Some(Cancellation::default()),
+            running: Running::default(),
+            outgoing: None,
+        }
+    }
+
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Returns a server sharing this one's handler, whose calls carry
+    /// `extensions` in their [`Context`]. It keeps track of its own requests,
+    /// as a session of its own.
+    pub fn with_extensions(&self, extensions: Extensions) -> Self {
+        Self {
+            handler: self.handler.clone(),
+            extensions,
+            cancellation: self.cancellation.clone(),
+            running: Running::default(),
+            outgoing: None,
+        }
+    }
+
+    pub fn extensions(&self) -> &Extensions {
+        &self.extensions
+    }
+
+    /// Sets how requests are cancelled; `None` passes cancellation
+    /// notifications to the handler like any other.
+    pub fn with_cancellation(mut self, cancellation: Option<Cancellation>) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    pub fn cancellation(&self) -> Option<&Cancellation> {
+        self.cancellation.as_ref()
+    }
+
+    /// Sends the progress reported by handlers to `outgoing`.
+    pub(crate) fn with_outgoing(mut self, outgoing: mpsc::UnboundedSender<Value>) -> Self {
+        self.outgoing = Some(outgoing);
+        self
+    }
+
+    /// Handles a serialized message, returning the serialized reply if any.
+    ///
+    /// Input that isn't valid JSON is answered with a Parse Error.
+    pub async fn handle_bytes(&self, bytes: &[u8]) -> Option<Vec<u8>> {
+        let reply = match serde_json::from_slice(bytes) {
+            Ok(value) => self.handle_value(value).await?,
+            Err(_) => Reply::Single(parse_error()),
+        };
+
+        Some(serde_json::to_vec(&reply).expect("replies always serialize"))
+    }
+
+    /// Handles a message, returning the reply if any.
+    pub async fn handle_value(&self, value: Value) -> Option<Reply> {
+        match value {
+            Value::Array(values) if values.is_empty() => Some(Reply::Single(
+                JsonRpcResponse::error(Header::v2(Id::Null), ErrorObject::invalid_request()),
+            )),
+            Value::Array(values) => {
+                // A batch with any 2.0 member is a 2.0 one, whose members
+                // are all answered in 2.0.
+                let version = if values.iter().any(|value| value["jsonrpc"] == "2.0") {
+                    Version::Two
+                } else {
+                    Version::One
+                };
+                let replies =
+                    future::join_all(values.into_iter().map(|value| {
+                        self.handle_entry(Message::from_entry(value), version.clone())
+                    }))
+                    .await;
+
+                Batch::new(replies.into_iter().flatten().collect()).map(Reply::Batch)
+            }
+            value => self
+                .handle_entry(Message::from_entry(value), Version::One)
+                .await
+                .map(Reply::Single),
+        }
+    }
+
+    /// Serves the messages received on `transport` until it closes.
+    ///
+    /// Messages are handled concurrently, and replies are sent as soon as
+    /// they are ready, in no particular order. Messages that fail to parse
+    /// are answered with a Parse Error. Handlers can report [`Progress`],
+    /// which is sent ahead of their reply.
+    ///
+    /// Every call is a session of its own, like one of
+    /// [`Server::with_extensions`]: its requests can only be cancelled over
+    /// `transport`.
+    pub async fn serve<T>(&self, transport: T) -> Result<(), TransportError>
+    where
+        T: Transport,
+    {
+        let (outgoing, mut notifications) = mpsc::unbounded();
+        let session = self
+            .with_extensions(self.extensions.clone())
+            .with_outgoing(outgoing);
+        let (mut sink, stream) = transport.split();
+        let mut stream = stream.fuse();
+        let mut in_flight = FuturesUnordered::new();
+
+        loop {
+            futures::select! {
+                message = stream.next() => match message {
+                    Some(Ok(message)) => in_flight.push(session.handle_value(message)),
+                    Some(Err(TransportError::Parse(_))) => {
+                        sink.send(to_message(Reply::Single(parse_error()))).await?
+                    }
+                    // Nothing the server sends expects an answer.
+                    Some(Err(TransportError::Unanswered { .. })) => {}
+                    Some(Err(error)) => return Err(error),
+                    None => break,
+                },
+                notification = notifications.select_next_some() => sink.send(notification).await?,
+                reply = in_flight.select_next_some() => {
+                    // The progress reported by a handler goes out before its
+                    // reply.
+                    while let Ok(notification) = notifications.try_recv() {
+                        sink.send(notification).await?;
+                    }
+                    if let Some(reply) = reply {
+                        sink.send(to_message(reply)).await?;
+                    }
+                }
+            }
+        }
+
+        while !in_flight.is_empty() {
+            futures::select! {
+                notification = notifications.select_next_some() => sink.send(notification).await?,
+                reply = in_flight.select_next_some() => {
+                    while let Ok(notification) = notifications.try_recv() {
+                        sink.send(notification).await?;
+                    }
+                    if let Some(reply) = reply {
+                        sink.send(to_message(reply)).await?;
+                    }
+                }
+            }
+        }
+
+        sink.close().await
+    }
+
+    /// Serves every connection yielded by `connections` concurrently, until
+    /// no more connections come in and all sessions have ended.
+    ///
+    /// The extensions of each connection are available to its calls through
+    /// their [`Context`]. A session failing doesn't affect the others, and
+    /// neither does failing to accept a connection: such errors are skipped.
+    pub async fn serve_connections<S, T>(&self, connections: S)
+    where
+        S: Stream<Item = io::Result<Connection<T>>>,
+        T: Transport,
+    {
+        let mut connections = pin!(connections.fuse());
+        let mut sessions = FuturesUnordered::new();
+
+        loop {
+            futures::select! {
+                connection = connections.next() => match connection {
+                    Some(Ok(connection)) => {
+                        let session = self.with_extensions(connection.extensions);
+                        sessions.push(async move { session.serve(connection.transport).await });
+                    }
+                    Some(Err(_)) => {}
+                    None => break,
+                },
+                _ = sessions.select_next_some() => {}
+            }
+        }
+
+        while sessions.next().await.is_some() {}
+    }
+
+    /// Handles a request, echoing its header back in the response.
+    pub async fn handle_request(&self, request: JsonRpcRequest<Request>) -> JsonRpcResponse<Value> {
+        let JsonRpcRequest { header, payload } = request;
+        let mut context =
+            Context::request(header.id.clone()).with_extensions(self.extensions.clone());
+        if let (Some(outgoing), Some(token)) = (&self.outgoing, progress::token(&payload)) {
+            context.progress = Some(Progress::new(
+                token,
+                header.jsonrpc.clone(),
+                outgoing.clone(),
+            ));
+        }
+
+        let Some(cancellation) = &self.cancellation else {
+            return match self.handler.call(payload, context).await {
+                Ok(result) => JsonRpcResponse::result(header, result),
+                Err(error) => JsonRpcResponse::error(header, error),
+            };
+        };
+
+        let (abort_handle, registration) = AbortHandle::new_pair();
+        context.abort_handle = Some(abort_handle.clone());
+        let _running = RunningGuard::insert(&self.running, header.id.clone(), abort_handle);
+        match Abortable::new(self.handler.call(payload, context), registration).await {
+            Ok(Ok(result)) => JsonRpcResponse::result(header, result),
+            Ok(Err(error)) => JsonRpcResponse::error(header, error),
+            Err(_) => JsonRpcResponse::error(header, cancellation.error()),
+        }
+    }
+
+    /// Aborts the handler of the request `id`, if it is still running.
+    fn cancel(&self, id: &Id) {
+        if let Some((_, abort_handle)) = lock(&self.running).requests.remove(id) {
+            abort_handle.abort();
+        }
+    }
+
+    /// Handles a message, answering it in `version` if it's an invalid
+    /// object that doesn't say which version it is.
+    async fn handle_entry(
+        &self,
+        entry: BatchEntry<Message<Request, Value>>,
+        version: Version,
+    ) -> Option<JsonRpcResponse<Value>> {
+        match entry {
+            BatchEntry::Valid(Message::Request(request)) => {
+                Some(self.handle_request(request).await)
+            }
+            BatchEntry::Valid(Message::Notification(notification)) => {
+                let cancelled = self
+                    .cancellation
+                    .as_ref()
+                    .and_then(|cancellation| cancellation.cancelled_id(&notification.payload));
+                if let Some(id) = cancelled {
+                    self.cancel(&id);
+                    return None;
+                }
+
+                let context = Context::notification().with_extensions(self.extensions.clone());
+                let _ = self.handler.call(notification.payload, context).await;
+                None
+            }
+            // Responses are not addressed to a server, so there is nothing to answer.
+            BatchEntry::Valid(Message::Response(_)) => None,
+            BatchEntry::Valid(Message::Batch(_)) => Some(JsonRpcResponse::error(
+                Header::v2(Id::Null),
+                ErrorObject::invalid_request(),
+            )),
+            BatchEntry::Invalid(entry) => {
+                let jsonrpc = match entry.value.get("jsonrpc") {
+                    Some(declared) => Version::deserialize(declared).unwrap_or(Version::Two),
+                    None if entry.value.is_object() => version,
+                    None => Version::Two,
+                };
+                let header = Header {
+                    jsonrpc,
+                    id: entry.id,
+                };
+                Some(JsonRpcResponse::error(
+                    header,
+                    ErrorObject::invalid_request(),
+                ))
+            }
+        }
+    }
+}
+
+/// Keeps the abort handle of a running request in the map of its server,
+/// until the request completes.
+struct RunningGuard<'a> {
+    running: &'a Running,
+    id: Id,
+    generation: u64,
+}
+
+impl<'a> RunningGuard<'a> {
+    fn insert(running: &'a Running, id: Id, abort_handle: AbortHandle) -> Self {
+        let mut requests = lock(running);
+        let generation = requests.next_generation;
+        requests.next_generation += 1;
+        requests
+            .requests
+            .insert(id.clone(), (generation, abort_handle));
+        Self {
+            running,
+            id,
+            generation,
+        }
+    }
+}
+
+impl Drop for RunningGuard<'_> {
+    fn drop(&mut self) {
+        let requests = &mut lock(self.running).requests;
+        let owned = requests
+            .get(&self.id)
+            .is_some_and(|(generation, _)| *generation == self.generation);
+        if owned {
+            requests.remove(&self.id);
+        }
+    }
+}
+
+fn lock(running: &Running) -> MutexGuard<'_, RunningRequests> {
+    running.lock().unwrap_or_else(|error| error.into_inner())
+}
+
+pub(crate) fn to_message(reply: Reply) -> Value {
+    serde_json::to_value(reply).expect("replies always serialize")
+}
+
+pub(crate) fn parse_error() -> JsonRpcResponse<Value> {
+    JsonRpcResponse::error(Header::v2(Id::Null), ErrorObject::parse_error())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Router;
+    use futures::executor::block_on;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn server() -> (Server<Router>, Arc<AtomicUsize>) {
+        let notified = Arc::new(AtomicUsize::new(0));
+        let mut router = Router::new();
+        router
+            .register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b))
+            .register("sum", |values: Vec<i64>| {
+                Ok::<_, ErrorObject>(values.iter().sum::<i64>())
+            })
+            .register("notify_hello", {
+                let notified = notified.clone();
+                move |_: Vec<i64>| {
+                    notified.fetch_add(1, Ordering::SeqCst);
+                    Ok::<_, ErrorObject>(())
+                }
+            })
+            .register_async("echo_id", |_: (), context: Context| async move {
+                Ok::<_, ErrorObject>(context.id().cloned())
+            });
+
+        (Server::new(router), notified)
+    }
+
+    fn handle(server: &Server<Router>, value: Value) -> Option<Value> {
+        block_on(server.handle_value(value)).map(|reply| serde_json::to_value(reply).unwrap())
+    }
+
+    #[test]
+    fn test_server_single_request() {
+        let (server, _) = server();
+        let reply = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1}),
+        );
+        assert_eq!(
+            reply,
+            Some(json!({"jsonrpc": "2.0", "result": 19, "id": 1}))
+        );
+    }
+
+    #[test]
+    fn test_server_async_handler_context() {
+        let (server, _) = server();
+        let reply = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "method": "echo_id", "id": "x"}),
+        );
+        assert_eq!(
+            reply,
+            Some(json!({"jsonrpc": "2.0", "result": "x", "id": "x"}))
+        );
+    }
+
+    #[test]
+    fn test_server_notification() {
+        let (server, notified) = server();
+        let reply = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "method": "notify_hello", "params": [7]}),
+        );
+        assert_eq!(reply, None);
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+
+        let reply = handle(&server, json!({"jsonrpc": "2.0", "method": "foobar"}));
+        assert_eq!(reply, None);
+    }
+
+    #[test]
+    fn test_server_method_not_found() {
+        let (server, _) = server();
+        let reply = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "method": "foobar", "id": "1"}),
+        );
+        let reply = reply.unwrap();
+        assert_eq!(reply["id"], json!("1"));
+        assert_eq!(reply["error"]["code"], json!(-32601));
+    }
+
+    #[test]
+    fn test_server_parse_error() {
+        let (server, _) = server();
+        let reply = block_on(
+            server.handle_bytes(br#"{"jsonrpc": "2.0", "method": "foobar, "params": "bar", "baz]"#),
+        );
+        let reply: Value = serde_json::from_slice(&reply.unwrap()).unwrap();
+        assert_eq!(
+            reply,
+            json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32700, "message": "Parse error"},
+                "id": null
+            })
+        );
+    }
+
+    #[test]
+    fn test_server_invalid_request() {
+        let (server, _) = server();
+        let reply = handle(
+            &server,
+            json!({"jsonrpc": "2.0", "method": 1, "params": "bar"}),
+        );
+        assert_eq!(
+            reply,
+            Some(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32600, "message": "Invalid Request"},
+                "id": null
+            }))
+        );
+    }
+
+    #[test]
+    fn test_server_empty_batch() {
+        let (server, _) = server();
+        let reply = handle(&server, json!([]));
+        assert_eq!(
+            reply,
+            Some(json!({
+                "jsonrpc": "2.0",
+                "error": {"code": -32600, "message": "Invalid Request"},
+                "id": null
+            }))
+        );
+    }
+
+    #[test]
+    fn test_server_invalid_batch() {
+        let (server, _) = server();
+        let reply = handle(&server, json!([1, 2, 3])).unwrap();
+        let invalid = json!({
+            "jsonrpc": "2.0",
+            "error": {"code": -32600, "message": "Invalid Request"},
+            "id": null
+        });
+        assert_eq!(reply, json!([invalid, invalid, invalid]));
+    }
+
+    #[test]
+    fn test_server_invalid_request_in_its_version() {
+        let (server, _) = server();
+        let invalid = |id| json!({"error": {"code": -32600, "message": "Invalid Request"}, "result": null, "id": id});
+
+        let reply = handle(&server, json!({"method": 1, "id": 1}));
+        assert_eq!(reply, Some(invalid(json!(1))));
+
+        let reply = handle(&server, json!([{"method": 1, "id": 1}, {"foo": "boo"}]));
+        assert_eq!(
+            reply,
+            Some(json!([invalid(json!(1)), invalid(Value::Null)]))
+        );
+    }
+
+    #[test]
+    fn test_server_mixed_batch() {
+        let (server, notified) = server();
+        let reply = block_on(server.handle_bytes(
+            br#"[
+                    {"jsonrpc": "2.0", "method": "sum", "params": [1,2,4], "id": "1"},
+                    {"jsonrpc": "2.0", "method": "notify_hello", "params": [7]},
+                    {"jsonrpc": "2.0", "method": "subtract", "params": [42,23], "id": "2"},
+                    {"foo": "boo"},
+                    {"jsonrpc": "2.0", "method": "foo.get", "params": {"name": "myself"}, "id": "5"}
+                ]"#,
+        ));
+
+        let reply: Value = serde_json::from_slice(&reply.unwrap()).unwrap();
+        assert_eq!(
+            reply,
+            json!([
+                {"jsonrpc": "2.0", "result": 7, "id": "1"},
+                {"jsonrpc": "2.0", "result": 19, "id": "2"},
+                {"jsonrpc": "2.0", "error": {"code": -32600, "message": "Invalid Request"}, "id": null},
+                {
+                    "jsonrpc": "2.0",
+                    "error": {"code": -32601, "message": "Method not found", "data": "foo.get"},
+                    "id": "5"
+                }
+            ])
+        );
+        assert_eq!(notified.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_server_notification_batch() {
+        let (server, notified) = server();
+        let reply = handle(
+            &server,
+            json!([
+                {"jsonrpc": "2.0", "method": "notify_hello", "params": [1]},
+                {"jsonrpc": "2.0", "method": "notify_hello", "params": [2]}
+            ]),
+        );
+        assert_eq!(reply, None);
+        assert_eq!(notified.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_server_ignores_responses() {
+        let (server, _) = server();
+        let reply = handle(&server, json!({"jsonrpc": "2.0", "result": 1, "id": 1}));
+        assert_eq!(reply, None);
+    }
+
+    #[test]
+    fn test_server_cancels_request() {
+        let contexts = Arc::new(Mutex::new(Vec::new()));
+        let mut router = Router::new();
+        router.register_async("slow", {
+            let contexts = contexts.clone();
+            move |_: (), context: Context| {
+                contexts.lock().unwrap().push(context);
+                future::pending::<Result<(), ErrorObject>>()
+            }
+        });
+        let server = Server::new(router);
+
+        let request = json!({"jsonrpc": "2.0", "method": "slow", "id": "a"});
+        let cancel = json!({"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": "a"}});
+        let (reply, cancelled) = block_on(future::join(
+            server.handle_value(request.clone()),
+            server.handle_value(cancel),
+        ));
+        assert!(cancelled.is_none());
+        assert_eq!(
+            serde_json::to_value(reply).unwrap(),
+            json!({"jsonrpc": "2.0", "error": {"code": -32800, "message": "Request cancelled"}, "id": "a"})
+        );
+        assert!(contexts.lock().unwrap()[0].is_cancelled());
+
+        let server = server.with_cancellation(Some(Cancellation::new("cancel", -1)));
+        let cancel = json!({"jsonrpc": "2.0", "method": "cancel", "params": {"id": "a"}});
+        let (reply, _) = block_on(future::join(
+            server.handle_value(request),
+            server.handle_value(cancel),
+        ));
+        assert_eq!(
+            serde_json::to_value(reply).unwrap()["error"]["code"],
+            json!(-1)
+        );
+    }
+
+    #[test]
+    fn test_server_cancels_request_sharing_an_id() {
+        let (release, released) = futures::channel::oneshot::channel::<()>();
+        let released = Mutex::new(Some(released));
+        let mut router = Router::new();
+        router
+            .register_async("wait", move |_: (), _: Context| {
+                let released = released.lock().unwrap().take().unwrap();
+                async move {
+                    let _ = released.await;
+                    Ok::<_, ErrorObject>("released")
+                }
+            })
+            .register_async("slow", |_: (), _: Context| {
+                future::pending::<Result<(), ErrorObject>>()
+            });
+        let server = Server::new(router);
+
+        let first = json!({"jsonrpc": "2.0", "method": "wait", "id": "a"});
+        let second = json!({"jsonrpc": "2.0", "method": "slow", "id": "a"});
+        let cancel = json!({"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": "a"}});
+        block_on(async {
+            let mut first = pin!(server.handle_value(first));
+            let mut second = pin!(server.handle_value(second));
+            assert!(futures::poll!(first.as_mut()).is_pending());
+            assert!(futures::poll!(second.as_mut()).is_pending());
+
+            // The first request finishing leaves the second one cancellable.
+            release.send(()).unwrap();
+            let reply = serde_json::to_value(first.await).unwrap();
+            assert_eq!(reply["result"], json!("released"));
+            assert!(lock(&server.running).requests.contains_key(&Id::from("a")));
+
+            let (reply, _) = future::join(second, server.handle_value(cancel)).await;
+            let reply = serde_json::to_value(reply).unwrap();
+            assert_eq!(reply["error"]["code"], json!(-32800));
+        });
+    }
+
+    #[test]
+    fn test_server_sessions_cancel_their_own_requests() {
+        let mut router = Router::new();
+        router
+            .register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b))
+            .register_async("slow", |_: (), _: Context| {
+                future::pending::<Result<(), ErrorObject>>()
+            });
+        let server = Server::new(router);
+        let (first_server_end, mut first) = crate::transport::Loopback::pair();
+        let (second_server_end, mut second) = crate::transport::Loopback::pair();
+
+        let clients = async move {
+            let subtract =
+                |id| json!({"jsonrpc": "2.0", "method": "subtract", "params": [2, 1], "id": id});
+            let cancel =
+                json!({"jsonrpc": "2.0", "method": "$/cancelRequest", "params": {"id": 1}});
+
+            // Requests are handled in order, so the slow one is running once
+            // the next one is answered.
+            let request = json!({"jsonrpc": "2.0", "method": "slow", "id": 1});
+            first.send(request).await.unwrap();
+            first.send(subtract(2)).await.unwrap();
+            assert_eq!(first.next().await.unwrap().unwrap()["id"], json!(2));
+
+            second.send(cancel.clone()).await.unwrap();
+            second.send(subtract(3)).await.unwrap();
+            assert_eq!(second.next().await.unwrap().unwrap()["id"], json!(3));
+
+            // The cancellation sent over the second connection left the
+            // request of the first one running.
+            first.send(subtract(4)).await.unwrap();
+            assert_eq!(first.next().await.unwrap().unwrap()["id"], json!(4));
+
+            first.send(cancel).await.unwrap();
+            let reply = first.next().await.unwrap().unwrap();
+            assert_eq!(reply["id"], json!(1));
+            assert_eq!(reply["error"]["code"], json!(-32800));
+        };
+
+        let (first_served, second_served, ()) = block_on(future::join3(
+            server.serve(first_server_end),
+            server.serve(second_server_end),
+            clients,
+        ));
+        first_served.unwrap();
+        second_served.unwrap();
+    }
+
+    #[test]
+    fn test_serve_connections_skips_accept_errors() {
+        let (server, _) = server();
+        let (server_end, mut client_end) = crate::transport::Loopback::pair();
+        let connections = futures::stream::iter([
+            Err(io::ErrorKind::ConnectionAborted.into()),
+            Ok(Connection::new(server_end)),
+        ]);
+
+        let client = async move {
+            let request =
+                json!({"jsonrpc": "2.0", "method": "subtract", "params": [42, 23], "id": 1});
+            client_end.send(request).await.unwrap();
+            let reply = client_end.next().await.unwrap().unwrap();
+            assert_eq!(reply["result"], json!(19));
+        };
+        block_on(future::join(server.serve_connections(connections), client));
+    }
+
+    #[test]
+    fn test_reply_into_responses() {
+        let response = JsonRpcResponse::result(Header::v2(1), json!(1));
+        assert_eq!(
+            Reply::Single(response.clone()).into_responses(),
+            vec![response.clone()]
+        );
+
+        let batch = Batch::new(vec![response.clone(), response.clone()]).unwrap();
+        assert_eq!(Reply::Batch(batch).into_responses().len(), 2);
+    }
+}
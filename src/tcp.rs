@@ -0,0 +1,160 @@
+//! JSON-RPC over TCP, framed by the codec of the caller's choosing.
+
+use std::io;
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+
+use crate::blocking::{BlockingIo, Incoming};
+use crate::codec::{Codec, Framed};
+use crate::transport::Connection;
+
+/// A TCP connection framed by the codec `C`.
+pub type TcpTransport<C> = Framed<BlockingIo, C>;
+
+/// Connects to `addr`, blocking until the connection is established.
+pub fn connect<C>(addr: impl ToSocketAddrs, codec: C) -> io::Result<TcpTransport<C>>
+where
+    C: Codec,
+{
+    TcpStream::connect(addr).and_then(|stream| transport(stream, codec))
+}
+
+/// Frames an established connection with `codec`.
+pub fn transport<C>(stream: TcpStream, codec: C) -> io::Result<TcpTransport<C>>
+where
+    C: Codec,
+{
+    BlockingIo::socket(stream).map(|io| Framed::new(io, codec))
+}
+
+/// Connects to `addr` on a thread of its own, for callers that mustn't block.
+#[cfg(any(feature = "http-client", feature = "websocket"))]
+pub(crate) async fn connect_io(addr: String) -> io::Result<BlockingIo> {
+    let (sender, receiver) = futures::channel::oneshot::channel();
+    std::thread::spawn(move || {
+        let _ = sender.send(TcpStream::connect(addr).and_then(BlockingIo::socket));
+    });
+    receiver
+        .await
+        .unwrap_or_else(|_| Err(io::ErrorKind::Interrupted.into()))
+}
+
+/// A socket listening for TCP connections.
+pub struct TcpListener {
+    listener: std::net::TcpListener,
+}
+
+impl TcpListener {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        std::net::TcpListener::bind(addr).map(|listener| Self { listener })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections on a thread of its own, framing each of them with
+    /// a clone of `codec`. The extensions of every connection hold the
+    /// [`SocketAddr`] of the peer.
+    ///
+    /// Serve them with [`Server::serve_connections`](crate::Server::serve_connections)
+    /// to run one session per connection.
+    pub fn incoming<C>(self, codec: C) -> Incoming<TcpTransport<C>>
+    where
+        C: Codec + Clone + Send + 'static,
+    {
+        Incoming::spawn(self.listener, move |stream, peer| {
+            let mut connection = Connection::new(transport(stream, codec.clone())?);
+            connection.extensions.insert(peer);
+            Ok(connection)
+        })
+    }
+
+    /// Like [`TcpListener::incoming`], but leaves the connections unframed.
+    #[cfg(any(feature = "http", feature = "websocket"))]
+    pub(crate) fn incoming_io(self) -> Incoming<BlockingIo> {
+        Incoming::spawn(self.listener, |stream, peer| {
+            let mut connection = Connection::new(BlockingIo::socket(stream)?);
+            connection.extensions.insert(peer);
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::codec::{ContentLength, Ndjson};
+    use crate::{
+        Client, ErrorObject, Header, JsonRpcRequest, JsonRpcResponse, Request, Router, Server,
+    };
+    use futures::executor::block_on;
+    use futures::{future, SinkExt, StreamExt};
+    use serde_json::json;
+    use std::thread;
+
+    /// Serves on a thread of its own, returning the address listened on.
+    fn serve<C>(codec: C) -> SocketAddr
+    where
+        C: Codec + Clone + Send + Unpin + 'static,
+    {
+        let mut router = Router::new();
+        router.register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b));
+        let server = Server::new(router);
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        let incoming = listener.incoming(codec);
+        thread::spawn(move || block_on(server.serve_connections(incoming)));
+        addr
+    }
+
+    #[test]
+    fn test_tcp_request_response() {
+        let addr = serve(ContentLength::new());
+        let mut transport = connect(addr, ContentLength::new()).unwrap();
+
+        let request = JsonRpcRequest {
+            header: Header::v2(1),
+            payload: Request::new("subtract", json!([42, 23])),
+        };
+        block_on(transport.send(serde_json::to_value(request).unwrap())).unwrap();
+
+        let reply = block_on(transport.next()).unwrap().unwrap();
+        let response: JsonRpcResponse<i64> = serde_json::from_value(reply).unwrap();
+        assert_eq!(response, JsonRpcResponse::result(Header::v2(1), 19));
+    }
+
+    #[test]
+    fn test_tcp_incoming_dropped_closes_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        drop(listener.incoming(Ndjson::new()));
+
+        // The accept thread notices within its poll interval.
+        for _ in 0..100 {
+            if TcpStream::connect(addr).is_err() {
+                return;
+            }
+            thread::sleep(std::time::Duration::from_millis(20));
+        }
+        panic!("the listener should be closed once its connections are dropped");
+    }
+
+    #[test]
+    fn test_tcp_client_sessions() {
+        let addr = serve(Ndjson::new());
+
+        let sessions = (0..3).map(|n| {
+            let (client, driver) = Client::connect(connect(addr, Ndjson::new()).unwrap());
+            let calls = async move {
+                let result = client.request::<_, i64>("subtract", [n, 1]).await;
+                assert_eq!(result.unwrap(), n - 1);
+            };
+            future::join(driver, calls)
+        });
+
+        for (driven, ()) in block_on(future::join_all(sessions)) {
+            driven.unwrap();
+        }
+    }
+}
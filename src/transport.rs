@@ -0,0 +1,267 @@
+//! Transports carry JSON-RPC messages between a [`Client`](crate::Client) and
+//! a [`Server`](crate::Server).
+
+use std::fmt;
+use std::io;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use futures::channel::mpsc;
+use futures::{Sink, Stream, StreamExt};
+use serde_json::Value;
+
+use crate::{Extensions, Id};
+
+/// Errors raised by a [`Transport`].
+#[derive(Debug)]
+pub enum TransportError {
+    /// The connection is closed.
+    Closed,
+    /// The underlying connection failed.
+    Io(io::Error),
+    /// A message could not be parsed as JSON. Unlike the other errors, this
+    /// one doesn't end the stream: the next message can still be read.
+    Parse(serde_json::Error),
+    /// A message was sent, but the requests it carried, with these ids, will
+    /// get no response, as when the HTTP request posting it fails. This
+    /// doesn't end the stream either: only those requests fail.
+    Unanswered { ids: Vec<Id>, error: io::Error },
+}
+
+impl fmt::Display for TransportError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Closed => f.write_str("connection closed"),
+            Self::Io(error) => write!(f, "connection failed: {error}"),
+            Self::Parse(error) => write!(f, "failed to parse message: {error}"),
+            Self::Unanswered { error, .. } => write!(f, "failed to send message: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Closed => None,
+            Self::Io(error) => Some(error),
+            Self::Parse(error) => Some(error),
+            Self::Unanswered { error, .. } => Some(error),
+        }
+    }
+}
+
+impl From<io::Error> for TransportError {
+    fn from(error: io::Error) -> Self {
+        Self::Io(error)
+    }
+}
+
+/// A sink and stream of framed JSON-RPC messages.
+///
+/// Every message is a JSON value: a single request, notification or
+/// response, or a batch of them.
+pub trait Transport:
+    Sink<Value, Error = TransportError>
+    + Stream<Item = Result<Value, TransportError>>
+    + Send
+    + Unpin
+    + 'static
+{
+}
+
+impl<T> Transport for T where
+    T: Sink<Value, Error = TransportError>
+        + Stream<Item = Result<Value, TransportError>>
+        + Send
+        + Unpin
+        + 'static
+{
+}
+
+/// A transport accepted by a listener, along with what is known about the
+/// peer on the other end.
+pub struct Connection<T> {
+    pub transport: T,
+    pub extensions: Extensions,
+}
+
+impl<T> Connection<T> {
+    pub fn new(transport: T) -> Self {
+        Self {
+            transport,
+            extensions: Extensions::new(),
+        }
+    }
+}
+
+/// One end of an in-process transport.
+///
+/// Messages sent on one end are received on the other, without any
+/// serialization. Dropping or closing an end ends the stream of the other.
+pub struct Loopback {
+    sender: mpsc::UnboundedSender<Value>,
+    receiver: mpsc::UnboundedReceiver<Value>,
+}
+
+impl Loopback {
+    /// Creates both ends of a connection.
+    pub fn pair() -> (Self, Self) {
+        let (left_sender, right_receiver) = mpsc::unbounded();
+        let (right_sender, left_receiver) = mpsc::unbounded();
+
+        let left = Self {
+            sender: left_sender,
+            receiver: left_receiver,
+        };
+        let right = Self {
+            sender: right_sender,
+            receiver: right_receiver,
+        };
+
+        (left, right)
+    }
+}
+
+impl Sink<Value> for Loopback {
+    type Error = TransportError;
+
+    fn poll_ready(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Pin::new(&mut self.sender)
+            .poll_ready(cx)
+            .map_err(|_| TransportError::Closed)
+    }
+
+    fn start_send(mut self: Pin<&mut Self>, item: Value) -> Result<(), Self::Error> {
+        Pin::new(&mut self.sender)
+            .start_send(item)
+            .map_err(|_| TransportError::Closed)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.sender.close_channel();
+        Poll::Ready(Ok(()))
+    }
+}
+
+impl Stream for Loopback {
+    type Item = Result<Value, TransportError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_next_unpin(cx).map(|item| item.map(Ok))
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::{
+        Client, ClientError, ErrorObject, Header, JsonRpcRequest, JsonRpcResponse, Request, Router,
+        Server,
+    };
+    use futures::executor::block_on;
+    use futures::{future, SinkExt};
+    use serde_json::json;
+
+    fn server() -> Server<Router> {
+        let mut router = Router::new();
+        router
+            .register("subtract", |(a, b): (i64, i64)| Ok::<_, ErrorObject>(a - b))
+            .register("fail", |_: ()| {
+                Err::<(), ErrorObject>(ErrorObject::internal_error())
+            });
+        Server::new(router)
+    }
+
+    #[test]
+    fn test_loopback_pair() {
+        let (mut left, mut right) = Loopback::pair();
+
+        block_on(left.send(json!({"ping": 1}))).unwrap();
+        assert_eq!(block_on(right.next()).unwrap().unwrap(), json!({"ping": 1}));
+
+        block_on(right.send(json!({"pong": 1}))).unwrap();
+        assert_eq!(block_on(left.next()).unwrap().unwrap(), json!({"pong": 1}));
+
+        drop(left);
+        assert!(block_on(right.next()).is_none());
+        assert!(matches!(
+            block_on(right.send(json!(null))),
+            Err(TransportError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_server_over_loopback() {
+        let (server_end, mut client_end) = Loopback::pair();
+        let server = server();
+
+        let client = async move {
+            let request = JsonRpcRequest {
+                header: Header::v2("a"),
+                payload: Request::new("subtract", json!([42, 23])),
+            };
+            client_end
+                .send(serde_json::to_value(request).unwrap())
+                .await
+                .unwrap();
+
+            let reply = client_end.next().await.unwrap().unwrap();
+            let response: JsonRpcResponse<i64> = serde_json::from_value(reply).unwrap();
+            assert_eq!(response, JsonRpcResponse::result(Header::v2("a"), 19));
+        };
+
+        let (served, ()) = block_on(future::join(server.serve(server_end), client));
+        served.unwrap();
+    }
+
+    #[test]
+    fn test_client_against_server_over_loopback() {
+        let (server_end, client_end) = Loopback::pair();
+        let server = server();
+        let (client, driver) = Client::connect(client_end);
+
+        let calls = async move {
+            let (first, second) = future::join(
+                client.request::<_, i64>("subtract", [42, 23]),
+                client.request::<_, i64>("subtract", [23, 42]),
+            )
+            .await;
+            assert_eq!(first.unwrap(), 19);
+            assert_eq!(second.unwrap(), -19);
+
+            let error = client.request::<_, ()>("fail", ()).await.unwrap_err();
+            assert!(
+                matches!(error, ClientError::Rpc(error) if error == ErrorObject::internal_error())
+            );
+
+            client.notify("subtract", [1, 1]).unwrap();
+        };
+
+        let (served, driven, ()) = block_on(future::join3(server.serve(server_end), driver, calls));
+        served.unwrap();
+        driven.unwrap();
+    }
+
+    #[test]
+    fn test_client_fails_pending_when_server_closes() {
+        let (mut server_end, client_end) = Loopback::pair();
+        let (client, driver) = Client::connect(client_end);
+
+        // Reads the request, then hangs up without answering it.
+        let server = async move {
+            server_end.next().await.unwrap().unwrap();
+        };
+
+        let (result, driven, ()) = block_on(future::join3(
+            client.request::<_, i64>("subtract", [1, 1]),
+            driver,
+            server,
+        ));
+        assert!(matches!(result, Err(ClientError::Closed)));
+        driven.unwrap();
+    }
+}
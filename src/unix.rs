@@ -0,0 +1,251 @@
+//! JSON-RPC over Unix domain sockets, framed by the codec of the caller's
+//! choosing.
+
+use std::fmt;
+use std::io;
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net::{SocketAddr, UnixStream};
+use std::path::Path;
+
+use crate::blocking::{BlockingIo, Incoming};
+use crate::codec::{Codec, Framed};
+use crate::transport::Connection;
+
+/// A Unix socket connection framed by the codec `C`.
+pub type UnixTransport<C> = Framed<BlockingIo, C>;
+
+/// The identity of the process on the other end of a Unix socket, as
+/// recorded by the kernel when the connection was made.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PeerCredentials {
+    pub uid: u32,
+    pub gid: u32,
+    /// The process id, on platforms that report it.
+    pub pid: Option<i32>,
+}
+
+impl PeerCredentials {
+    pub fn of(stream: &UnixStream) -> io::Result<Self> {
+        sys::peer_credentials(stream.as_raw_fd())
+    }
+}
+
+/// Why the [`PeerCredentials`] of an accepted connection couldn't be read.
+/// The extensions of the connection hold it in their place.
+#[derive(Debug)]
+pub struct PeerCredentialsError(pub io::Error);
+
+impl fmt::Display for PeerCredentialsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to read peer credentials: {}", self.0)
+    }
+}
+
+impl std::error::Error for PeerCredentialsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+/// Connects to the socket at `path`.
+pub fn connect<C>(path: impl AsRef<Path>, codec: C) -> io::Result<UnixTransport<C>>
+where
+    C: Codec,
+{
+    UnixStream::connect(path).and_then(|stream| transport(stream, codec))
+}
+
+/// Frames an established connection with `codec`.
+pub fn transport<C>(stream: UnixStream, codec: C) -> io::Result<UnixTransport<C>>
+where
+    C: Codec,
+{
+    BlockingIo::socket(stream).map(|io| Framed::new(io, codec))
+}
+
+/// A socket listening for Unix socket connections.
+pub struct UnixListener {
+    listener: std::os::unix::net::UnixListener,
+}
+
+impl UnixListener {
+    pub fn bind(path: impl AsRef<Path>) -> io::Result<Self> {
+        std::os::unix::net::UnixListener::bind(path).map(|listener| Self { listener })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections on a thread of its own, framing each of them with
+    /// a clone of `codec`. The extensions of every connection hold the
+    /// [`PeerCredentials`] of the peer, or a [`PeerCredentialsError`] where
+    /// they can't be read, such as on platforms that don't report them.
+    ///
+    /// Serve them with [`Server::serve_connections`](crate::Server::serve_connections)
+    /// to run one session per connection.
+    pub fn incoming<C>(self, codec: C) -> Incoming<UnixTransport<C>>
+    where
+        C: Codec + Clone + Send + 'static,
+    {
+        Incoming::spawn(self.listener, move |stream, _| {
+            let credentials = PeerCredentials::of(&stream);
+            let mut connection = Connection::new(transport(stream, codec.clone())?);
+            match credentials {
+                Ok(credentials) => connection.extensions.insert(credentials),
+                Err(error) => connection.extensions.insert(PeerCredentialsError(error)),
+            };
+            Ok(connection)
+        })
+    }
+}
+
+#[cfg(any(target_os = "linux", target_os = "android"))]
+mod sys {
+    use std::io;
+    use std::os::unix::io::RawFd;
+
+    use super::PeerCredentials;
+
+    pub(super) fn peer_credentials(fd: RawFd) -> io::Result<PeerCredentials> {
+        let mut credentials = libc::ucred {
+            pid: 0,
+            uid: 0,
+            gid: 0,
+        };
+        let mut length = std::mem::size_of::<libc::ucred>() as libc::socklen_t;
+
+        // SAFETY: `credentials` and `length` are valid for writes, and
+        // `length` holds the size of `credentials`.
+        let result = unsafe {
+            libc::getsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                libc::SO_PEERCRED,
+                (&mut credentials as *mut libc::ucred).cast(),
+                &mut length,
+            )
+        };
+        if result != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(PeerCredentials {
+            uid: credentials.uid,
+            gid: credentials.gid,
+            pid: Some(credentials.pid),
+        })
+    }
+}
+
+#[cfg(any(
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+))]
+mod sys {
+    use std::io;
+    use std::os::unix::io::RawFd;
+
+    use super::PeerCredentials;
+
+    pub(super) fn peer_credentials(fd: RawFd) -> io::Result<PeerCredentials> {
+        let (mut uid, mut gid) = (0, 0);
+
+        // SAFETY: `uid` and `gid` are valid for writes.
+        if unsafe { libc::getpeereid(fd, &mut uid, &mut gid) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        Ok(PeerCredentials {
+            uid,
+            gid,
+            pid: None,
+        })
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd",
+    target_os = "openbsd",
+    target_os = "netbsd",
+    target_os = "dragonfly"
+)))]
+mod sys {
+    use std::io;
+    use std::os::unix::io::RawFd;
+
+    use super::PeerCredentials;
+
+    pub(super) fn peer_credentials(_: RawFd) -> io::Result<PeerCredentials> {
+        Err(io::ErrorKind::Unsupported.into())
+    }
+}
+
+#[cfg(all(test, feature = "client", feature = "server"))]
+mod tests {
+    use super::*;
+    use crate::codec::Ndjson;
+    use crate::{Client, Context, ErrorObject, Router, Server};
+    use futures::executor::block_on;
+    use futures::future;
+    use serde_json::Value;
+    use std::os::unix::fs::MetadataExt;
+    use std::path::PathBuf;
+    use std::thread;
+
+    fn socket_path(name: &str) -> PathBuf {
+        let path =
+            std::env::temp_dir().join(format!("jsonrpc-types-{}-{name}.sock", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        path
+    }
+
+    #[test]
+    fn test_unix_peer_credentials() {
+        let (left, right) = UnixStream::pair().unwrap();
+        let credentials = PeerCredentials::of(&left).unwrap();
+        assert_eq!(credentials, PeerCredentials::of(&right).unwrap());
+
+        // Files we create are owned by our own uid.
+        let path = socket_path("owner");
+        std::fs::write(&path, b"").unwrap();
+        assert_eq!(credentials.uid, std::fs::metadata(&path).unwrap().uid());
+        let _ = std::fs::remove_file(&path);
+        if cfg!(target_os = "linux") {
+            assert_eq!(credentials.pid, Some(std::process::id() as i32));
+        }
+    }
+
+    #[test]
+    fn test_unix_handlers_see_peer_credentials() {
+        let mut router = Router::new();
+        router.register_async("whoami", |_: (), context: Context| async move {
+            let credentials = context.extensions().get::<PeerCredentials>().copied();
+            credentials
+                .map(|credentials| credentials.uid)
+                .ok_or_else(ErrorObject::<Value>::internal_error)
+        });
+        let server = Server::new(router);
+
+        let path = socket_path("whoami");
+        let listener = UnixListener::bind(&path).unwrap();
+        let incoming = listener.incoming(Ndjson::new());
+        thread::spawn(move || block_on(server.serve_connections(incoming)));
+
+        let (client, driver) = Client::connect(connect(&path, Ndjson::new()).unwrap());
+        let calls = async move { client.request::<_, u32>("whoami", ()).await };
+        let (driven, uid) = block_on(future::join(driver, calls));
+        driven.unwrap();
+
+        assert_eq!(uid.unwrap(), std::fs::metadata(&path).unwrap().uid());
+        let _ = std::fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,881 @@
+//! JSON-RPC over WebSocket, one message per text frame.
+//!
+//! Either side may send at any time, so servers can push notifications to
+//! their clients through a [`Notifier`], as subscriptions need. Pings are
+//! answered with pongs, and close frames with a close frame of our own.
+
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use futures::channel::mpsc;
+use futures::io::{AsyncRead, AsyncWrite, AsyncWriteExt};
+use futures::{future, Sink, Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use sha1::{Digest, Sha1};
+
+use crate::blocking::BlockingIo;
+use crate::http::{invalid, read_head, Head, Url};
+use crate::tcp::{self, TcpListener};
+use crate::transport::{Connection, TransportError};
+use crate::{JsonRpcNotification, Request};
+
+/// The largest message accepted unless configured otherwise: 16 MiB.
+pub const DEFAULT_MAX_MESSAGE_LENGTH: usize = 16 * 1024 * 1024;
+
+/// A WebSocket over a TCP connection.
+pub type WebSocketTransport = WebSocket<BlockingIo>;
+
+const ACCEPT_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+const MAX_PENDING_HANDSHAKES: usize = 64;
+const READ_CHUNK: usize = 8 * 1024;
+const WRITE_HIGH_WATER_MARK: usize = 8 * 1024;
+
+const CONTINUATION: u8 = 0x0;
+const TEXT: u8 = 0x1;
+const BINARY: u8 = 0x2;
+const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xa;
+
+const NORMAL_CLOSURE: u16 = 1000;
+const PROTOCOL_ERROR: u16 = 1002;
+const UNSUPPORTED_DATA: u16 = 1003;
+const INVALID_PAYLOAD: u16 = 1007;
+const MESSAGE_TOO_BIG: u16 = 1009;
+
+const BAD_REQUEST: &str =
+    "HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\nConnection: close\r\n\r\n";
+const UPGRADE_REQUIRED: &str = "HTTP/1.1 426 Upgrade Required\r\nSec-WebSocket-Version: 13\r\n\
+                                Content-Length: 0\r\nConnection: close\r\n\r\n";
+
+/// Connects to a `ws://` URL. `wss://` isn't supported.
+pub async fn connect(url: &str) -> io::Result<WebSocketTransport> {
+    let addr = Url::parse(url, "ws").ok_or_else(|| invalid_url(url))?.addr;
+    handshake(tcp::connect_io(addr).await?, url).await
+}
+
+/// Performs the opening handshake of a client over an established
+/// connection to the server at `url`.
+pub async fn handshake<T>(mut io: T, url: &str) -> io::Result<WebSocket<T>>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let url = Url::parse(url, "ws").ok_or_else(|| invalid_url(url))?;
+    let key = BASE64.encode(random_bytes::<16>());
+    let request = format!(
+        "GET {} HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n",
+        url.path, url.host
+    );
+    io.write_all(request.as_bytes()).await?;
+    io.flush().await?;
+
+    let mut buffer = Vec::new();
+    let head = read_head(&mut io, &mut buffer)
+        .await?
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+    let upgraded = head.start_line.split(' ').nth(1) == Some("101")
+        && head.has_token("Upgrade", "websocket")
+        && head.has_token("Connection", "upgrade")
+        && head.header("Sec-WebSocket-Accept") == Some(accept_key(&key).as_str());
+    if !upgraded {
+        return Err(invalid(format!(
+            "server refused the WebSocket handshake: {}",
+            head.start_line
+        )));
+    }
+
+    Ok(WebSocket::new(io, Role::Client, buffer))
+}
+
+/// Performs the opening handshake of a server over a new connection.
+///
+/// Requests that aren't a valid WebSocket upgrade are answered with
+/// `400 Bad Request`, or `426 Upgrade Required` for other protocol
+/// versions, and fail with [`io::ErrorKind::InvalidData`].
+pub async fn accept<T>(mut io: T) -> io::Result<WebSocket<T>>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut buffer = Vec::new();
+    let head = read_head(&mut io, &mut buffer)
+        .await?
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+
+    let response = match opening_key(&head) {
+        Ok(key) => format!(
+            "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+             Sec-WebSocket-Accept: {}\r\n\r\n",
+            accept_key(key)
+        ),
+        Err(response) => {
+            io.write_all(response.as_bytes()).await?;
+            io.close().await?;
+            return Err(invalid("invalid WebSocket handshake"));
+        }
+    };
+    io.write_all(response.as_bytes()).await?;
+    io.flush().await?;
+
+    Ok(WebSocket::new(io, Role::Server, buffer))
+}
+
+/// The `Sec-WebSocket-Key` of an opening handshake, or the response
+/// rejecting it.
+fn opening_key(head: &Head) -> Result<&str, &'static str> {
+    let mut parts = head.start_line.split(' ');
+    let (Some("GET"), Some(_), Some("HTTP/1.1"), None) =
+        (parts.next(), parts.next(), parts.next(), parts.next())
+    else {
+        return Err(BAD_REQUEST);
+    };
+    if !head.has_token("Upgrade", "websocket") || !head.has_token("Connection", "upgrade") {
+        return Err(BAD_REQUEST);
+    }
+    if head.header("Sec-WebSocket-Version") != Some("13") {
+        return Err(UPGRADE_REQUIRED);
+    }
+    head.header("Sec-WebSocket-Key")
+        .filter(|key| key.len() == 24)
+        .ok_or(BAD_REQUEST)
+}
+
+fn invalid_url(url: &str) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidInput,
+        format!("invalid WebSocket URL {url:?}"),
+    )
+}
+
+/// A socket listening for WebSocket connections.
+pub struct WebSocketListener {
+    listener: TcpListener,
+}
+
+impl WebSocketListener {
+    pub fn bind(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        TcpListener::bind(addr).map(|listener| Self { listener })
+    }
+
+    pub fn local_addr(&self) -> io::Result<SocketAddr> {
+        self.listener.local_addr()
+    }
+
+    /// Accepts connections and performs their opening handshakes, up to 64 at
+    /// a time. Connections that fail the handshake are dropped. The
+    /// extensions of every connection hold the [`SocketAddr`] of the peer and
+    /// a [`Notifier`] to push messages to it.
+    ///
+    /// Serve them with [`Server::serve_connections`](crate::Server::serve_connections)
+    /// to run one session per connection.
+    pub fn incoming(
+        self,
+    ) -> impl Stream<Item = io::Result<Connection<WebSocketTransport>>> + Send + 'static {
+        self.listener
+            .incoming_io()
+            .map(|accepted| async move {
+                let Connection {
+                    transport,
+                    mut extensions,
+                } = match accepted {
+                    Ok(connection) => connection,
+                    Err(error) => return Some(Err(error)),
+                };
+                let websocket = accept(transport).await.ok()?;
+                extensions.insert(websocket.notifier());
+                Some(Ok(Connection {
+                    transport: websocket,
+                    extensions,
+                }))
+            })
+            .buffer_unordered(MAX_PENDING_HANDSHAKES)
+            .filter_map(future::ready)
+    }
+}
+
+/// Sends messages to the peer of a [`WebSocket`] at any time, independently
+/// of the requests it makes.
+///
+/// Connections accepted by [`WebSocketListener::incoming`] carry one in their
+/// extensions, so handlers can keep it to notify subscribers later.
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    sender: mpsc::UnboundedSender<Value>,
+}
+
+impl Notifier {
+    /// Queues a notification for `method`. Fails with
+    /// [`TransportError::Closed`] once the connection is closed.
+    pub fn notify<P>(&self, method: &str, params: P) -> Result<(), TransportError>
+    where
+        P: Serialize,
+    {
+        let notification = JsonRpcNotification::v2(Request::new(method, params));
+        let message = serde_json::to_value(notification).map_err(io::Error::from)?;
+        self.send(message)
+    }
+
+    /// Queues any message, such as a request or a batch.
+    pub fn send(&self, message: Value) -> Result<(), TransportError> {
+        self.sender
+            .unbounded_send(message)
+            .map_err(|_| TransportError::Closed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Role {
+    Client,
+    Server,
+}
+
+/// A WebSocket carrying one JSON-RPC message per text frame.
+///
+/// Pings are answered and pongs ignored as they are read. Receiving a close
+/// frame ends the stream, once the close frame is echoed; closing the sink
+/// sends one. Protocol violations fail the connection with the matching
+/// status code, and end the stream after an [`io::ErrorKind::InvalidData`]
+/// error. A text frame that isn't valid JSON is a recoverable
+/// [`TransportError::Parse`].
+pub struct WebSocket<T> {
+    io: T,
+    role: Role,
+    max_message_length: usize,
+    read_buffer: Vec<u8>,
+    write_buffer: Vec<u8>,
+    /// The text of a fragmented message, up to its last frame.
+    fragments: Option<Vec<u8>>,
+    notifier: Notifier,
+    pushed: mpsc::UnboundedReceiver<Value>,
+    /// The failure of a write made while polling the stream, reported by
+    /// the next use of the stream or the sink.
+    write_error: Option<TransportError>,
+    write_failed: bool,
+    close_sent: bool,
+    eof: bool,
+}
+
+impl<T> WebSocket<T> {
+    fn new(io: T, role: Role, read_buffer: Vec<u8>) -> Self {
+        let (sender, pushed) = mpsc::unbounded();
+        Self {
+            io,
+            role,
+            max_message_length: DEFAULT_MAX_MESSAGE_LENGTH,
+            read_buffer,
+            write_buffer: Vec::new(),
+            fragments: None,
+            notifier: Notifier { sender },
+            pushed,
+            write_error: None,
+            write_failed: false,
+            close_sent: false,
+            eof: false,
+        }
+    }
+
+    /// Fails the connection with status 1009 when a message is longer than
+    /// `max_message_length` bytes.
+    pub fn with_max_message_length(mut self, max_message_length: usize) -> Self {
+        self.max_message_length = max_message_length;
+        self
+    }
+
+    pub fn max_message_length(&self) -> usize {
+        self.max_message_length
+    }
+
+    /// A handle to send messages alongside those sent through the sink. They
+    /// are written while the stream is polled; failing to write them ends
+    /// the stream with the error, and closes the notifier.
+    pub fn notifier(&self) -> Notifier {
+        self.notifier.clone()
+    }
+
+    pub fn get_ref(&self) -> &T {
+        &self.io
+    }
+
+    fn send_frame(&mut self, opcode: u8, payload: &[u8]) {
+        encode_frame(opcode, payload, self.role, &mut self.write_buffer);
+    }
+
+    fn send_close(&mut self, code: u16) {
+        if !self.close_sent {
+            self.send_frame(CLOSE, &code.to_be_bytes());
+            self.close_sent = true;
+            self.pushed.close();
+        }
+    }
+
+    /// Handles a frame, returning the text of the message it completes.
+    fn receive(&mut self, frame: Frame) -> Result<Option<Vec<u8>>, Failure> {
+        match (frame.opcode, self.fragments.as_mut()) {
+            (PING, _) => {
+                if !self.close_sent {
+                    self.send_frame(PONG, &frame.payload);
+                }
+                Ok(None)
+            }
+            (PONG, _) => Ok(None),
+            (CLOSE, _) => {
+                let code = match frame.payload[..] {
+                    [] => NORMAL_CLOSURE,
+                    [high, low, ..] => u16::from_be_bytes([high, low]),
+                    [_] => return Err(Failure::protocol("close frame is truncated")),
+                };
+                self.send_close(code);
+                self.eof = true;
+                Ok(None)
+            }
+            (TEXT, None) if frame.fin => Ok(Some(frame.payload)),
+            (TEXT, None) => {
+                self.fragments = Some(frame.payload);
+                Ok(None)
+            }
+            (CONTINUATION, Some(fragments)) => {
+                if fragments.len() + frame.payload.len() > self.max_message_length {
+                    return Err(Failure::too_big());
+                }
+                fragments.extend_from_slice(&frame.payload);
+                Ok(frame.fin.then(|| self.fragments.take()).flatten())
+            }
+            (BINARY, _) => Err(Failure {
+                code: UNSUPPORTED_DATA,
+                reason: "binary messages are not supported",
+            }),
+            (TEXT | CONTINUATION, _) => Err(Failure::protocol("unexpected frame in a message")),
+            _ => Err(Failure::protocol("unknown opcode")),
+        }
+    }
+}
+
+impl<T> WebSocket<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    fn poll_write_buffer(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), TransportError>> {
+        while !self.write_buffer.is_empty() {
+            let written = ready!(Pin::new(&mut self.io).poll_write(cx, &self.write_buffer))?;
+            if written == 0 {
+                return Poll::Ready(Err(io::Error::from(io::ErrorKind::WriteZero).into()));
+            }
+            self.write_buffer.drain(..written);
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Writes whatever can be written without waiting: pongs, close frames
+    /// and pushed messages. The first failure is kept for
+    /// [`WebSocket::write_error`] to report, and stops further pushes.
+    fn write_eagerly(&mut self, cx: &mut Context<'_>) {
+        while let Poll::Ready(Some(message)) = self.pushed.poll_next_unpin(cx) {
+            self.send_frame(TEXT, message.to_string().as_bytes());
+        }
+        if self.write_failed {
+            return;
+        }
+        if let Poll::Ready(Err(error)) = self.poll_write_buffer(cx) {
+            self.write_error = Some(error);
+            self.write_failed = true;
+            self.pushed.close();
+        }
+    }
+
+    /// Reports the failure of an earlier eager write, once.
+    fn write_error(&mut self) -> Result<(), TransportError> {
+        self.write_error.take().map_or(Ok(()), Err)
+    }
+
+    /// Fails the connection, returning the error that ends the stream.
+    fn fail(&mut self, failure: Failure, cx: &mut Context<'_>) -> TransportError {
+        self.send_close(failure.code);
+        self.read_buffer.clear();
+        self.fragments = None;
+        self.eof = true;
+        self.write_eagerly(cx);
+        invalid(failure.reason).into()
+    }
+}
+
+impl<T> Stream for WebSocket<T>
+where
+    T: AsyncRead + AsyncWrite + Unpin,
+{
+    type Item = Result<Value, TransportError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        loop {
+            this.write_eagerly(cx);
+            if let Err(error) = this.write_error() {
+                this.eof = true;
+                return Poll::Ready(Some(Err(error)));
+            }
+            if this.eof {
+                return Poll::Ready(None);
+            }
+
+            let received = decode_frame(&mut this.read_buffer, this.role, this.max_message_length)
+                .and_then(|frame| frame.map(|frame| this.receive(frame)).transpose());
+            match received {
+                Ok(Some(Some(text))) => {
+                    if std::str::from_utf8(&text).is_err() {
+                        let failure = Failure {
+                            code: INVALID_PAYLOAD,
+                            reason: "text message is not UTF-8",
+                        };
+                        return Poll::Ready(Some(Err(this.fail(failure, cx))));
+                    }
+                    let message = serde_json::from_slice(&text).map_err(TransportError::Parse);
+                    return Poll::Ready(Some(message));
+                }
+                Ok(Some(None)) => continue,
+                Ok(None) => {}
+                Err(failure) => return Poll::Ready(Some(Err(this.fail(failure, cx)))),
+            }
+
+            let start = this.read_buffer.len();
+            this.read_buffer.resize(start + READ_CHUNK, 0);
+            let read = Pin::new(&mut this.io).poll_read(cx, &mut this.read_buffer[start..]);
+            let read = match read {
+                Poll::Ready(Ok(read)) => read,
+                Poll::Ready(Err(error)) => {
+                    this.read_buffer.truncate(start);
+                    return Poll::Ready(Some(Err(error.into())));
+                }
+                Poll::Pending => {
+                    this.read_buffer.truncate(start);
+                    return Poll::Pending;
+                }
+            };
+
+            this.read_buffer.truncate(start + read);
+            if read == 0 {
+                this.eof = true;
+            }
+        }
+    }
+}
+
+impl<T> Sink<Value> for WebSocket<T>
+where
+    T: AsyncWrite + Unpin,
+{
+    type Error = TransportError;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write_error()?;
+        if this.write_buffer.len() >= WRITE_HIGH_WATER_MARK {
+            ready!(this.poll_write_buffer(cx))?;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Value) -> Result<(), Self::Error> {
+        let this = self.get_mut();
+        if this.close_sent {
+            return Err(TransportError::Closed);
+        }
+        this.send_frame(TEXT, item.to_string().as_bytes());
+        Ok(())
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.write_error()?;
+        ready!(this.poll_write_buffer(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.io).poll_flush(cx)).map_err(Into::into))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        let this = self.get_mut();
+        this.send_close(NORMAL_CLOSURE);
+        ready!(this.poll_write_buffer(cx))?;
+        Poll::Ready(ready!(Pin::new(&mut this.io).poll_close(cx)).map_err(Into::into))
+    }
+}
+
+struct Frame {
+    fin: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Why a connection is failed: the status code of the close frame, and the
+/// reason given by the error ending the stream.
+#[derive(Debug)]
+struct Failure {
+    code: u16,
+    reason: &'static str,
+}
+
+impl Failure {
+    fn protocol(reason: &'static str) -> Self {
+        Self {
+            code: PROTOCOL_ERROR,
+            reason,
+        }
+    }
+
+    fn too_big() -> Self {
+        Self {
+            code: MESSAGE_TOO_BIG,
+            reason: "message is too long",
+        }
+    }
+}
+
+/// Takes the next complete frame out of `buffer`, unmasking its payload.
+/// Frames are checked as soon as their header is read, so that oversized
+/// frames are refused before they are buffered.
+fn decode_frame(
+    buffer: &mut Vec<u8>,
+    role: Role,
+    max_length: usize,
+) -> Result<Option<Frame>, Failure> {
+    let [first, second, ..] = buffer[..] else {
+        return Ok(None);
+    };
+    let fin = first & 0x80 != 0;
+    let opcode = first & 0x0f;
+    if first & 0x70 != 0 {
+        return Err(Failure::protocol("reserved bits are set"));
+    }
+
+    // Clients mask every frame they send, and servers none.
+    let masked = second & 0x80 != 0;
+    match (role, masked) {
+        (Role::Server, false) => return Err(Failure::protocol("client frame is not masked")),
+        (Role::Client, true) => return Err(Failure::protocol("server frame is masked")),
+        _ => {}
+    }
+
+    let (length, mut offset) = match second & 0x7f {
+        126 if buffer.len() >= 4 => (u64::from(u16::from_be_bytes([buffer[2], buffer[3]])), 4),
+        127 if buffer.len() >= 10 => {
+            let length: [u8; 8] = buffer[2..10].try_into().expect("8 bytes");
+            (u64::from_be_bytes(length), 10)
+        }
+        126 | 127 => return Ok(None),
+        length => (u64::from(length), 2),
+    };
+    if opcode & 0x8 != 0 && (!fin || length > 125) {
+        return Err(Failure::protocol("control frame is fragmented or too long"));
+    }
+    let length = match usize::try_from(length) {
+        Ok(length) if length <= max_length => length,
+        _ => return Err(Failure::too_big()),
+    };
+
+    let mask = if masked {
+        let Some(mask) = buffer.get(offset..offset + 4) else {
+            return Ok(None);
+        };
+        let mask: [u8; 4] = mask.try_into().expect("4 bytes");
+        offset += 4;
+        Some(mask)
+    } else {
+        None
+    };
+    if buffer.len() < offset + length {
+        return Ok(None);
+    }
+
+    let mut payload: Vec<u8> = buffer.drain(..offset + length).skip(offset).collect();
+    if let Some(mask) = mask {
+        apply_mask(&mut payload, mask);
+    }
+    Ok(Some(Frame {
+        fin,
+        opcode,
+        payload,
+    }))
+}
+
+/// Appends an unfragmented frame, masked if sent by a client.
+fn encode_frame(opcode: u8, payload: &[u8], role: Role, dst: &mut Vec<u8>) {
+    let mask_bit = if role == Role::Client { 0x80 } else { 0 };
+    dst.push(0x80 | opcode);
+    match payload.len() {
+        length @ 0..=125 => dst.push(mask_bit | length as u8),
+        length @ 126..=0xffff => {
+            dst.push(mask_bit | 126);
+            dst.extend_from_slice(&(length as u16).to_be_bytes());
+        }
+        length => {
+            dst.push(mask_bit | 127);
+            dst.extend_from_slice(&(length as u64).to_be_bytes());
+        }
+    }
+
+    let start = dst.len();
+    if role == Role::Client {
+        let mask = random_bytes::<4>();
+        dst.extend_from_slice(&mask);
+        dst.extend_from_slice(payload);
+        apply_mask(&mut dst[start + 4..], mask);
+    } else {
+        dst.extend_from_slice(payload);
+    }
+}
+
+fn apply_mask(bytes: &mut [u8], mask: [u8; 4]) {
+    for (byte, mask) in bytes.iter_mut().zip(mask.iter().cycle()) {
+        *byte ^= mask;
+    }
+}
+
+/// The `Sec-WebSocket-Accept` answering `key`.
+fn accept_key(key: &str) -> String {
+    let digest = Sha1::new()
+        .chain_update(key)
+        .chain_update(ACCEPT_GUID)
+        .finalize();
+    BASE64.encode(digest)
+}
+
+/// Bytes for handshake keys and masks, which must be unpredictable.
+fn random_bytes<const N: usize>() -> [u8; N] {
+    let mut bytes = [0; N];
+    getrandom::fill(&mut bytes).expect("the system random number generator is available");
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::testing::Duplex;
+    use futures::executor::block_on;
+    use futures::SinkExt;
+    use serde_json::json;
+
+    /// A frame as a client sends it, with the given first byte.
+    fn client_frame(first: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = Vec::new();
+        encode_frame(first & 0x0f, payload, Role::Client, &mut frame);
+        frame[0] = first;
+        frame
+    }
+
+    fn server(input: Vec<Vec<u8>>) -> WebSocket<Duplex> {
+        WebSocket::new(Duplex::new(input.concat()), Role::Server, Vec::new())
+    }
+
+    #[test]
+    fn test_accept_key() {
+        // The example of RFC 6455.
+        assert_eq!(
+            accept_key("dGhlIHNhbXBsZSBub25jZQ=="),
+            "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+        );
+    }
+
+    #[test]
+    fn test_websocket_frames() {
+        let mut websocket = server(vec![
+            client_frame(0x89, b"hi"),
+            client_frame(0x01, b"{\"a\":"),
+            client_frame(0x80, b"1}"),
+            client_frame(0x81, b"{"),
+            client_frame(0x8a, b""),
+            client_frame(0x88, &1001u16.to_be_bytes()),
+            client_frame(0x81, b"{}"),
+        ]);
+
+        assert_eq!(
+            block_on(websocket.next()).unwrap().unwrap(),
+            json!({"a": 1})
+        );
+        assert!(matches!(
+            block_on(websocket.next()),
+            Some(Err(TransportError::Parse(_)))
+        ));
+        assert!(block_on(websocket.next()).is_none());
+
+        // The pong echoes the ping, and the close frame its status code.
+        assert_eq!(
+            websocket.get_ref().output(),
+            [&[0x8a, 2, b'h', b'i'][..], &[0x88, 2, 0x03, 0xe9]].concat()
+        );
+        assert!(websocket.notifier().is_closed());
+        assert!(matches!(
+            websocket.start_send_unpin(json!({})),
+            Err(TransportError::Closed)
+        ));
+    }
+
+    #[test]
+    fn test_websocket_failures() {
+        let cases = [
+            (vec![vec![0x81, 2, b'{', b'}']], PROTOCOL_ERROR),
+            (vec![client_frame(0x82, b"{}")], UNSUPPORTED_DATA),
+            (vec![client_frame(0x81, b"\xff")], INVALID_PAYLOAD),
+            (vec![client_frame(0x09, b"")], PROTOCOL_ERROR),
+            (vec![client_frame(0x80, b"")], PROTOCOL_ERROR),
+            (vec![client_frame(0xc1, b"{}")], PROTOCOL_ERROR),
+            (vec![client_frame(0x81, &[b' '; 17])], MESSAGE_TOO_BIG),
+            (
+                vec![
+                    client_frame(0x01, &[b' '; 9]),
+                    client_frame(0x80, &[b' '; 9]),
+                ],
+                MESSAGE_TOO_BIG,
+            ),
+        ];
+
+        for (input, code) in cases {
+            let mut websocket = server(input).with_max_message_length(16);
+            assert!(matches!(
+                block_on(websocket.next()),
+                Some(Err(TransportError::Io(error))) if error.kind() == io::ErrorKind::InvalidData
+            ));
+            assert!(block_on(websocket.next()).is_none());
+
+            let output = &websocket.get_ref().output();
+            assert_eq!(output[..2], [0x88, 2]);
+            assert_eq!(u16::from_be_bytes([output[2], output[3]]), code);
+        }
+    }
+
+    #[test]
+    fn test_websocket_reports_failed_pushes() {
+        let mut websocket = server(Vec::new());
+        websocket.io.broken = true;
+        let notifier = websocket.notifier();
+        notifier.notify("update", [1]).unwrap();
+
+        let Some(Err(TransportError::Io(error))) = block_on(websocket.next()) else {
+            panic!("the failed push should end the stream with its error");
+        };
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+        assert!(matches!(
+            notifier.notify("update", [2]),
+            Err(TransportError::Closed)
+        ));
+        assert!(block_on(websocket.next()).is_none());
+    }
+
+    #[test]
+    fn test_websocket_client_masks_frames() {
+        let mut websocket = WebSocket::new(Duplex::new(Vec::new()), Role::Client, Vec::new());
+        websocket.notifier().notify("tick", json!([1])).unwrap();
+        block_on(websocket.send(json!({"jsonrpc": "2.0", "method": "ping", "id": 1}))).unwrap();
+        assert!(block_on(websocket.next()).is_none());
+        block_on(websocket.close()).unwrap();
+
+        let mut output = websocket.get_ref().output();
+        let mut decode = || {
+            decode_frame(&mut output, Role::Server, usize::MAX)
+                .unwrap()
+                .unwrap()
+        };
+        let frame = decode();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&frame.payload).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "ping", "id": 1})
+        );
+        let frame = decode();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&frame.payload).unwrap(),
+            json!({"jsonrpc": "2.0", "method": "tick", "params": [1]})
+        );
+        let frame = decode();
+        assert_eq!((frame.opcode, frame.payload), (CLOSE, vec![0x03, 0xe8]));
+        assert!(output.is_empty());
+    }
+
+    #[test]
+    fn test_websocket_accept() {
+        let request = "GET /rpc HTTP/1.1\r\nHost: localhost\r\nUpgrade: websocket\r\n\
+                       Connection: keep-alive, Upgrade\r\nSec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+                       Sec-WebSocket-Version: 13\r\n\r\n";
+        let input = [request.as_bytes(), &client_frame(0x81, b"[1]")].concat();
+
+        let mut websocket = block_on(accept(Duplex::new(input))).unwrap();
+        assert_eq!(block_on(websocket.next()).unwrap().unwrap(), json!([1]));
+
+        let output = String::from_utf8(websocket.get_ref().output()).unwrap();
+        assert!(output.starts_with("HTTP/1.1 101 Switching Protocols\r\n"));
+        assert!(output.contains("Sec-WebSocket-Accept: s3pPLMBiTxaQ9kYGzzhZRbK+xOo=\r\n"));
+
+        let request = request.replace("Version: 13", "Version: 8");
+        let mut io = Duplex::new(request.into_bytes());
+        assert!(block_on(accept(&mut io)).is_err());
+        assert!(io
+            .output()
+            .starts_with(b"HTTP/1.1 426 Upgrade Required\r\n"));
+
+        let mut io = Duplex::new(b"POST / HTTP/1.1\r\n\r\n".to_vec());
+        assert!(block_on(accept(&mut io)).is_err());
+        assert!(io.output().starts_with(b"HTTP/1.1 400 Bad Request\r\n"));
+    }
+
+    #[cfg(all(feature = "client", feature = "server"))]
+    #[test]
+    fn test_websocket_server_push() {
+        use crate::{Client, Context as CallContext, ErrorObject, Router, Server};
+        use std::thread;
+
+        let mut router = Router::new();
+        router
+            .register("echo", |value: Value| Ok::<_, ErrorObject>(value))
+            .register_async("subscribe", |_: (), context: CallContext| async move {
+                let Some(notifier) = context.extensions().get::<Notifier>().cloned() else {
+                    return Err(ErrorObject::<Value>::internal_error());
+                };
+                thread::spawn(move || {
+                    for tick in 0..3 {
+                        notifier.notify("tick", [tick]).unwrap();
+                    }
+                });
+                Ok("subscribed")
+            });
+        let server = Server::new(router);
+
+        let listener = WebSocketListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("ws://{}/rpc", listener.local_addr().unwrap());
+        let incoming = listener.incoming();
+        thread::spawn(move || block_on(server.serve_connections(incoming)));
+
+        let (client, driver) = Client::connect(block_on(connect(&url)).unwrap());
+        let calls = async move { client.request::<_, Value>("echo", json!(["hi"])).await };
+        let (driven, echoed) = block_on(future::join(driver, calls));
+        driven.unwrap();
+        assert_eq!(echoed.unwrap(), json!(["hi"]));
+
+        let mut websocket = block_on(connect(&url)).unwrap();
+        let request = json!({"jsonrpc": "2.0", "method": "subscribe", "id": 1});
+        block_on(websocket.send(request)).unwrap();
+
+        let mut messages: Vec<Value> = (0..4)
+            .map(|_| block_on(websocket.next()).unwrap().unwrap())
+            .collect();
+        let response = messages
+            .iter()
+            .position(|message| message.get("id").is_some());
+        assert_eq!(
+            messages.remove(response.unwrap()),
+            json!({"jsonrpc": "2.0", "result": "subscribed", "id": 1})
+        );
+        assert_eq!(
+            messages,
+            (0..3)
+                .map(|tick| json!({"jsonrpc": "2.0", "method": "tick", "params": [tick]}))
+                .collect::<Vec<_>>()
+        );
+
+        block_on(websocket.close()).unwrap();
+        assert!(block_on(websocket.next()).is_none());
+    }
+}